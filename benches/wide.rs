@@ -0,0 +1,77 @@
+#[macro_use]
+extern crate criterion;
+extern crate jsonpath;
+#[macro_use]
+extern crate serde_json;
+
+use criterion::Criterion;
+use jsonpath::{IndexedDocument, Selector};
+use serde_json::value::RawValue;
+use serde_json::{Map, Value};
+
+const WIDTH: usize = 100_000;
+
+// An object with WIDTH members and an array with WIDTH elements
+fn wide_document() -> Value {
+    let mut members = Map::new();
+    for i in 0..WIDTH {
+        members.insert(format!("key{}", i), json!({ "value": i }));
+    }
+    let items: Vec<Value> = (0..WIDTH).map(|i| json!({ "value": i })).collect();
+    json!({ "members": members, "items": items })
+}
+
+fn bench_find(c: &mut Criterion, name: &str, expression: &str, document: &Value) {
+    let selector = Selector::new(expression).unwrap();
+    c.bench_function(name, |b| b.iter(|| selector.find(document).count()));
+}
+
+fn wide(c: &mut Criterion) {
+    let document = wide_document();
+    bench_find(c, "named child", "$.members.key99999.value", &document);
+    bench_find(c, "indexed child", "$.items[99999].value", &document);
+    bench_find(c, "slice", "$.items[50000:50009].value", &document);
+    bench_find(c, "slice to", "$.items[:10].value", &document);
+    bench_find(c, "slice from", "$.items[99990:].value", &document);
+    bench_find(
+        c,
+        "absolute operand",
+        "$.items[?(@.value >= $.items[?(@.value == 99990)].value)].value",
+        &document,
+    );
+}
+
+// Parsing the whole text then finding, against scanning the text for the matches only
+fn raw(c: &mut Criterion) {
+    let mut document = wide_document();
+    document["header"] = json!({ "id": 7 });
+    let text = serde_json::to_string(&document).unwrap();
+    let selector = Selector::new("$.header.id").unwrap();
+
+    c.bench_function("parse and find", |b| {
+        b.iter(|| {
+            let value: Value = serde_json::from_str(&text).unwrap();
+            selector.find(&value).count()
+        })
+    });
+    c.bench_function("find in str", |b| {
+        b.iter(|| selector.find_in_str::<&RawValue>(&text).unwrap().len())
+    });
+}
+
+// Walking every descendant for `..value`, against looking it up in an index built once
+fn indexed(c: &mut Criterion) {
+    let document = wide_document();
+    let index = IndexedDocument::new(&document);
+    let selector = Selector::new("$..value").unwrap();
+
+    c.bench_function("descendants", |b| {
+        b.iter(|| selector.find(&document).count())
+    });
+    c.bench_function("indexed descendants", |b| {
+        b.iter(|| selector.find(&index).count())
+    });
+}
+
+criterion_group!(benches, wide, raw, indexed);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate jsonpath;
+extern crate serde_json;
+
+use jsonpath::Selector;
+use serde_json::Value;
+
+// The first line is the expression, the rest is the document
+fuzz_target!(|data: &[u8]| {
+    let input = match std::str::from_utf8(data) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+    let mut parts = input.splitn(2, '\n');
+    let expression = parts.next().unwrap_or("");
+    let document = parts.next().unwrap_or("null");
+
+    if let (Ok(selector), Ok(value)) = (
+        Selector::new(expression),
+        serde_json::from_str::<Value>(document),
+    ) {
+        let _ = selector.find(&value).count();
+        let _ = selector.find_strict(&value).count();
+    }
+});
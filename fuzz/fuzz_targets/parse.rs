@@ -0,0 +1,13 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate jsonpath;
+
+use jsonpath::Selector;
+
+// Any expression must be either accepted or rejected with an error
+fuzz_target!(|data: &[u8]| {
+    if let Ok(expression) = std::str::from_utf8(data) {
+        let _ = Selector::new(expression);
+    }
+});
@@ -0,0 +1,268 @@
+//! Derive and compile-time checked macros for the `jsonpath` crate.
+//!
+//! `jsonpath!("$.store.books[?(@.price < 10)]")` parses the expression while the calling crate
+//! is compiled, reports parse errors at the literal and expands to a lazily initialised
+//! `&'static jsonpath::Selector`:
+//!
+//! ```ignore
+//! let cheap = jsonpath!("$.store.books[?(@.price < 10)].title");
+//! let titles: Vec<&Value> = cheap.find(&json).collect();
+//! ```
+//!
+//! `#[derive(JsonPathExtract)]` implements `jsonpath::JsonPathExtract` for a struct with
+//! named fields. Every field is annotated with the JSONPath expression its value is taken from:
+//!
+//! ```ignore
+//! #[derive(JsonPathExtract)]
+//! struct Store {
+//!     #[jsonpath("$.store.bicycle.price")]
+//!     price: f64,
+//!
+//!     #[jsonpath("$.store.books.*.title")]
+//!     titles: Vec<String>,
+//!
+//!     #[jsonpath("$.store.books[0].isbn")]
+//!     isbn: Option<String>,
+//! }
+//!
+//! let store = Store::extract(&json)?;
+//! ```
+//!
+//! `Vec<T>` fields collect every match, `Option<T>` fields accept zero or one match and any
+//! other field requires exactly one match. Matches are converted with `serde`, so every
+//! field type must implement `DeserializeOwned`. The expressions are validated at compile
+//! time and the selectors are compiled once, on the first call of `extract`.
+extern crate jsonpath;
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use std::ops::Range;
+use syn::{Data, DeriveInput, Fields, LitStr, Type};
+
+#[proc_macro]
+pub fn jsonpath(input: TokenStream) -> TokenStream {
+    let path = syn::parse_macro_input!(input as LitStr);
+    if let Err(err) = validate(&path) {
+        let error = compile_error(err);
+        return quote!({ #error }).into();
+    }
+
+    let tokens = quote! {
+        {
+            ::jsonpath::__private::lazy_static! {
+                static ref SELECTOR: ::jsonpath::Selector = ::jsonpath::Selector::new(#path)
+                    .expect("JSONPath is validated at compile time");
+            }
+            &*SELECTOR
+        }
+    };
+    tokens.into()
+}
+
+#[proc_macro_derive(JsonPathExtract, attributes(jsonpath))]
+pub fn derive_json_path_extract(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    match expand_extract(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => compile_error(err).into(),
+    }
+}
+
+// Same as `syn::Error::to_compile_error`, but without referring to `::core`,
+// which is not in scope for crates on the 2015 edition.
+fn compile_error(err: syn::Error) -> TokenStream2 {
+    let errors = err.into_iter().map(|err| {
+        let msg = err.to_string();
+        quote_spanned!(err.span() => compile_error!(#msg);)
+    });
+    quote!(#(#errors)*)
+}
+
+// How the matches of a field selector are turned into the field value
+enum Arity {
+    One,
+    Optional,
+    All,
+}
+
+struct ExtractField<'a> {
+    ident: &'a syn::Ident,
+    path: LitStr,
+    arity: Arity,
+}
+
+fn expand_extract(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "JsonPathExtract can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "JsonPathExtract can only be derived for structs",
+            ))
+        }
+    };
+
+    let fields = fields
+        .iter()
+        .map(|field| {
+            let ident = field.ident.as_ref().expect("named field");
+            Ok(ExtractField {
+                ident,
+                path: field_path(field)?,
+                arity: field_arity(&field.ty),
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let paths = fields.iter().map(|field| &field.path);
+    let initializers = fields.iter().enumerate().map(|(index, field)| {
+        let ident = field.ident;
+        let path = &field.path;
+        let field_name = ident.to_string();
+        let helper = match field.arity {
+            Arity::One => quote!(one),
+            Arity::Optional => quote!(optional),
+            Arity::All => quote!(all),
+        };
+        quote! {
+            #ident: ::jsonpath::extract::#helper(
+                &SELECTORS[#index],
+                root,
+                #field_name,
+                #path,
+            )?
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::jsonpath::JsonPathExtract for #name #ty_generics #where_clause {
+            fn extract(root: &::jsonpath::__private::Value) -> ::jsonpath::Result<Self> {
+                ::jsonpath::__private::lazy_static! {
+                    static ref SELECTORS: ::std::vec::Vec<::jsonpath::Selector> = vec![
+                        #(
+                            ::jsonpath::Selector::new(#paths)
+                                .expect("JSONPath is validated at compile time")
+                        ),*
+                    ];
+                }
+
+                Ok(#name {
+                    #(#initializers),*
+                })
+            }
+        }
+    })
+}
+
+fn field_path(field: &syn::Field) -> syn::Result<LitStr> {
+    let mut paths = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("jsonpath"));
+
+    let attr = match paths.next() {
+        Some(attr) => attr,
+        None => {
+            return Err(syn::Error::new_spanned(
+                field,
+                "missing #[jsonpath(\"...\")] attribute",
+            ))
+        }
+    };
+    if let Some(duplicate) = paths.next() {
+        return Err(syn::Error::new_spanned(
+            duplicate,
+            "duplicate #[jsonpath] attribute",
+        ));
+    }
+    let path = attr.parse_args::<LitStr>()?;
+    validate(&path)?;
+    Ok(path)
+}
+
+// Runs the parser of the `jsonpath` crate on the literal
+fn validate(path: &LitStr) -> syn::Result<()> {
+    match jsonpath::Selector::new(&path.value()) {
+        Ok(_) => Ok(()),
+        Err(err) => {
+            let span = match *err.kind() {
+                jsonpath::ErrorKind::Parse(ref parse_error) => {
+                    offending_range(&path.token().to_string(), parse_error.offset())
+                        .and_then(|range| path.token().subspan(range))
+                }
+                _ => None,
+            };
+            Err(syn::Error::new(
+                span.unwrap_or_else(|| path.span()),
+                format!("invalid JSONPath: {}", err),
+            ))
+        }
+    }
+}
+
+// Range of the source of a string literal holding the character at `offset` of its value,
+// or the closing quote when the error is at the end. Literals with escapes do not spell
+// their value byte for byte, their errors point at the whole literal. Compilers that cannot
+// point inside literals, stable ones among them, do so too.
+fn offending_range(source: &str, offset: usize) -> Option<Range<usize>> {
+    let start = source.find('"')? + 1;
+    let end = source.rfind('"')?;
+    let value = source.get(start..end)?;
+    if value.contains('\\') && !source.starts_with('r') {
+        return None;
+    }
+    let len = value
+        .get(offset..)?
+        .chars()
+        .next()
+        .map_or(1, char::len_utf8);
+    Some(start + offset..start + offset + len)
+}
+
+fn field_arity(ty: &Type) -> Arity {
+    if let Type::Path(ref type_path) = *ty {
+        if let Some(segment) = type_path.path.segments.last() {
+            if segment.ident == "Vec" {
+                return Arity::All;
+            }
+            if segment.ident == "Option" {
+                return Arity::Optional;
+            }
+        }
+    }
+    Arity::One
+}
+
+#[cfg(test)]
+mod tests {
+    use super::offending_range;
+
+    #[test]
+    fn test_offending_range() {
+        let source = r#""$.books[?(@.price <> 3)]""#;
+        assert_eq!(offending_range(source, 19), Some(20..21));
+        assert_eq!(&source[20..21], ">");
+
+        let raw = r###"r#"$.a["#"###;
+        assert_eq!(offending_range(raw, 4), Some(7..8));
+        assert_eq!(&raw[7..8], "\"");
+
+        assert_eq!(offending_range(r#""$.a\u{2e}""#, 3), None);
+    }
+}
@@ -0,0 +1,100 @@
+extern crate jsonpath;
+#[macro_use]
+extern crate jsonpath_derive;
+extern crate serde_json;
+
+use jsonpath::{ErrorKind, JsonPathExtract};
+use serde_json::Value;
+
+fn data() -> Value {
+    serde_json::from_str(include_str!("../../tests/data.json")).unwrap()
+}
+
+#[derive(Debug, JsonPathExtract)]
+struct Store {
+    #[jsonpath("$.store.bicycle.price")]
+    price: f64,
+
+    #[jsonpath("$.store.books.*.title")]
+    titles: Vec<String>,
+
+    #[jsonpath("$.store.books[2].isbn")]
+    isbn: Option<String>,
+
+    #[jsonpath("$.store.books[0].isbn")]
+    missing_isbn: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, JsonPathExtract)]
+struct Bicycle {
+    #[jsonpath("$.store.bicycle.brand")]
+    brand: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, JsonPathExtract)]
+struct Author {
+    #[jsonpath("$.store.books.*.author")]
+    name: String,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, JsonPathExtract)]
+struct Prices {
+    #[jsonpath("$.store.books.*.price")]
+    prices: Vec<u32>,
+}
+
+#[test]
+fn test_extract() {
+    let store = Store::extract(&data()).unwrap();
+    assert_eq!(store.price, 19.95);
+    assert_eq!(
+        store.titles,
+        vec![
+            "Sayings of the Century",
+            "Sword of Honour",
+            "Moby Dick",
+            "The Lord of the Rings",
+        ]
+    );
+    assert_eq!(store.isbn, Some("0-553-21311-3".to_owned()));
+    assert_eq!(store.missing_isbn, None);
+}
+
+#[test]
+fn test_extract_missing_field() {
+    let err = Bicycle::extract(&data()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::MissingField(ref field, ref path) => {
+            assert_eq!(field, "brand");
+            assert_eq!(path, "$.store.bicycle.brand");
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_extract_ambiguous_field() {
+    let err = Author::extract(&data()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::AmbiguousField(ref field, _, count) => {
+            assert_eq!(field, "name");
+            assert_eq!(count, 4);
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_extract_invalid_field() {
+    let err = Prices::extract(&data()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::InvalidField(ref field, ref path, _) => {
+            assert_eq!(field, "prices");
+            assert_eq!(path, "$.store.books.*.price");
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
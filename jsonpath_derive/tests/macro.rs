@@ -0,0 +1,34 @@
+#[macro_use]
+extern crate jsonpath_derive;
+extern crate jsonpath;
+extern crate serde_json;
+extern crate trybuild;
+
+use jsonpath::Selector;
+use serde_json::Value;
+
+fn data() -> Value {
+    serde_json::from_str(include_str!("../../tests/data.json")).unwrap()
+}
+
+fn cheap_books() -> &'static Selector {
+    jsonpath!("$.store.books[?(@.price < 10)].title")
+}
+
+#[test]
+fn test_jsonpath() {
+    let data = data();
+    let titles: Vec<&Value> = cheap_books().find(&data).collect();
+    assert_eq!(titles, vec!["Sayings of the Century", "Moby Dick"]);
+}
+
+#[test]
+fn test_jsonpath_is_initialised_once() {
+    assert!(::std::ptr::eq(cheap_books(), cheap_books()));
+}
+
+#[test]
+fn test_invalid_jsonpath() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+#[macro_use]
+extern crate jsonpath_derive;
+extern crate jsonpath;
+
+#[derive(JsonPathExtract)]
+struct Store {
+    #[jsonpath("store.bicycle.price")]
+    price: f64,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[macro_use]
+extern crate jsonpath_derive;
+extern crate jsonpath;
+
+fn main() {
+    let _selector = jsonpath!("$.books[?(@.price <> 3)]");
+}
@@ -0,0 +1,664 @@
+//! The syntax tree of selectors, to build them without formatting strings.
+//!
+//! A `Path` is built from `Path::root()` by chaining segments, and filters from
+//! `Expr::path_rel` or `Expr::path_abs` followed by a comparison. Names and literals are
+//! escaped when the path is rendered, so any string is safe to use:
+//!
+//! ```
+//! use jsonpath::ast::{Expr, Path};
+//!
+//! let path = Path::root()
+//!     .child("store")
+//!     .index(2)
+//!     .filter(Expr::path_rel(["price"]).lt(10));
+//! assert_eq!(path.to_string(), "$.store[2][?(@.price < 10)]");
+//!
+//! let selector = Path::root().child("it's").to_selector().unwrap();
+//! assert_eq!(selector.to_string(), r"$['it\'s']");
+//! ```
+//!
+//! Parsing an expression gives its tree back, see `Selector::to_path`.
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+use std::str::FromStr;
+
+use errors::*;
+use parser::{check_nesting, parse};
+pub use plan::Operator;
+use selector::Selector;
+use structs::{Criteria, Criterion, Pattern};
+
+// `&&` distributed over `||` multiplies the alternatives of a filter, so their number is
+// limited to keep built conditions from expanding without bound.
+const MAX_ALTERNATIVES: usize = 1024;
+
+/// A path: where it starts, and the segments applied from there.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    pub start: Start,
+    pub segments: Vec<Segment>,
+}
+
+/// The value a path starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Start {
+    /// `$`, the root of the document
+    Root,
+    /// `@`, the value a filter tests
+    Current,
+}
+
+/// A segment of a path, and how it is spelled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// `.name`, or `['name']` for names that are not identifiers
+    Child(String),
+    /// `.*`
+    Wildcard,
+    /// `[3]`
+    Index(usize),
+    /// `[-1]`, a negative integer key of CBOR, MessagePack and YAML maps
+    Integer(i128),
+    /// `[h'cafe']`, a byte string key of CBOR and MessagePack maps
+    Bytes(Vec<u8>),
+    /// `[start:last]`, where the element at `last` is included
+    Slice(usize, usize),
+    /// `[:end]`, where the element at `end` is excluded
+    SliceTo(usize),
+    /// `[start:]`
+    SliceFrom(usize),
+    /// `..`, the value and all its descendants, to which the next segment applies
+    Descendant,
+    /// `[?(condition)]`
+    Filter(Expr),
+}
+
+/// The condition of a filter.
+///
+/// Filters are written as alternatives of conjunctions, `?(a) && ?(b) || ?(c)`; other
+/// combinations of `And` and `Or` are distributed into that form when rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// `?(path operator operand)`
+    Compare(Path, Operator, Operand),
+    /// `?(a) && ?(b)`
+    And(Vec<Expr>),
+    /// `?(a) || ?(b)`
+    Or(Vec<Expr>),
+}
+
+/// The right side of a comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// `'text'`
+    String(String),
+    /// A finite number that is not negative
+    Number(f64),
+    /// `[1, 2]` or `['a', 'b']`, compares true when any of its items does
+    Array(Vec<Operand>),
+    /// A path that starts at `$`
+    Path(Path),
+    /// The pattern of `/pattern/`, with an inline `(?i)` flag in front for `/pattern/i`
+    Regex(String),
+}
+
+/// The left side of a comparison, waiting for its operator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    path: Path,
+}
+
+impl Path {
+    /// `$`
+    pub fn root() -> Self {
+        Path {
+            start: Start::Root,
+            segments: vec![],
+        }
+    }
+
+    /// `@`
+    pub fn current() -> Self {
+        Path {
+            start: Start::Current,
+            segments: vec![],
+        }
+    }
+
+    /// Any segment, e.g. one matched from another path.
+    pub fn segment(mut self, segment: Segment) -> Self {
+        self.segments.push(segment);
+        self
+    }
+
+    /// `.name`, the member `name` of an object.
+    pub fn child<S: Into<String>>(self, name: S) -> Self {
+        self.segment(Segment::Child(name.into()))
+    }
+
+    /// `.*`, every element of an array or member of an object.
+    pub fn wildcard(self) -> Self {
+        self.segment(Segment::Wildcard)
+    }
+
+    /// `[index]`, the element at `index` of an array.
+    pub fn index(self, index: usize) -> Self {
+        self.segment(Segment::Index(index))
+    }
+
+    /// `[key]`, the member of a CBOR, MessagePack or YAML map with an integer key. Keys
+    /// that are not negative are written as indexes, which match them as well.
+    pub fn integer(self, key: i128) -> Self {
+        match key {
+            key if key < 0 => self.segment(Segment::Integer(key)),
+            key => self.index(key as usize),
+        }
+    }
+
+    /// `[h'cafe']`, the member of a CBOR or MessagePack map with a byte string key.
+    pub fn key<B: Into<Vec<u8>>>(self, bytes: B) -> Self {
+        self.segment(Segment::Bytes(bytes.into()))
+    }
+
+    /// Elements of an array in `range`, e.g. `.slice(1..3)` for `[1:2]`.
+    pub fn slice<R: RangeBounds<usize>>(self, range: R) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        };
+        let segment = match range.end_bound() {
+            Bound::Unbounded => Segment::SliceFrom(start),
+            Bound::Included(&last) if start <= last => Segment::Slice(start, last),
+            Bound::Excluded(&end) if start == 0 => Segment::SliceTo(end),
+            Bound::Excluded(&end) if start < end => Segment::Slice(start, end - 1),
+            // An empty range
+            _ => Segment::SliceTo(0),
+        };
+        self.segment(segment)
+    }
+
+    /// `..`, the value and all its descendants. Must be followed by another segment.
+    pub fn descendants(self) -> Self {
+        self.segment(Segment::Descendant)
+    }
+
+    /// `[?(condition)]`, the children for which `condition` holds.
+    pub fn filter(self, condition: Expr) -> Self {
+        self.segment(Segment::Filter(condition))
+    }
+
+    /// Compiles the path, which must start at `$`, into a selector.
+    ///
+    /// Paths that cannot be written as an expression are rejected with
+    /// `ErrorKind::Compile`: a `..` not followed by another segment, an integer key that
+    /// is not negative, a filter without comparisons or with more than 1024 alternatives
+    /// once `&&` is distributed over `||`, a negative or non-finite number, a mixed or
+    /// empty array, or a path operand that starts at `@`.
+    pub fn to_selector(&self) -> Result<Selector> {
+        if self.start != Start::Root {
+            bail!(ErrorKind::Compile(
+                "a selector must start with `$`".to_owned()
+            ));
+        }
+        self.check()?;
+
+        let criteria = self.criteria();
+        let selector = Selector::from_criteria(&criteria, self.clone())?;
+        // The selector displays as an expression that parses back to it
+        check_nesting(&selector.to_string())?;
+        Ok(selector)
+    }
+
+    fn check(&self) -> Result<()> {
+        for (i, segment) in self.segments.iter().enumerate() {
+            match *segment {
+                Segment::Descendant => match self.segments.get(i + 1) {
+                    Some(&Segment::Descendant) | None => bail!(ErrorKind::Compile(
+                        "`..` must be followed by another segment".to_owned()
+                    )),
+                    Some(_) => {}
+                },
+                Segment::Integer(key) if key >= 0 => bail!(ErrorKind::Compile(format!(
+                    "integer keys must be negative, found {}",
+                    key
+                ))),
+                Segment::Filter(ref condition) => condition.check()?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn criteria(&self) -> Vec<Criterion> {
+        let start = match self.start {
+            Start::Root => Criterion::Root,
+            Start::Current => Criterion::Element,
+        };
+        let mut criteria = vec![start];
+        for segment in &self.segments {
+            criteria.push(match *segment {
+                Segment::Child(ref name) => Criterion::NamedChild(name.clone()),
+                Segment::Wildcard => Criterion::AnyChild,
+                Segment::Index(index) => Criterion::IndexedChild(index),
+                Segment::Integer(key) => Criterion::IntegerChild(key),
+                Segment::Bytes(ref bytes) => Criterion::BytesChild(bytes.clone()),
+                Segment::Slice(start, last) => Criterion::Slice(start..last),
+                Segment::SliceTo(end) => Criterion::SliceTo(..end),
+                Segment::SliceFrom(start) => Criterion::SliceFrom(start),
+                Segment::Descendant => Criterion::Descendant,
+                Segment::Filter(ref condition) => Criterion::Filter(condition.criteria()),
+            });
+        }
+        criteria
+    }
+
+    pub(crate) fn from_criteria(criteria: &[Criterion]) -> Result<Self> {
+        let start = match criteria.first() {
+            Some(&Criterion::Root) => Start::Root,
+            Some(&Criterion::Element) => Start::Current,
+            _ => bail!(ErrorKind::Compile(
+                "a path must start with `$` or `@`".to_owned()
+            )),
+        };
+        let segments = criteria[1..]
+            .iter()
+            .map(|criterion| {
+                let segment = match *criterion {
+                    Criterion::NamedChild(ref name) => Segment::Child(name.clone()),
+                    Criterion::AnyChild => Segment::Wildcard,
+                    Criterion::IndexedChild(index) => Segment::Index(index),
+                    Criterion::IntegerChild(key) => Segment::Integer(key),
+                    Criterion::BytesChild(ref bytes) => Segment::Bytes(bytes.clone()),
+                    Criterion::Slice(ref range) => Segment::Slice(range.start, range.end),
+                    Criterion::SliceTo(ref range_to) => Segment::SliceTo(range_to.end),
+                    Criterion::SliceFrom(from) => Segment::SliceFrom(from),
+                    Criterion::Descendant => Segment::Descendant,
+                    Criterion::Filter(ref criteria) => {
+                        Segment::Filter(Expr::from_criteria(criteria)?)
+                    }
+                    ref criterion => bail!(ErrorKind::Compile(format!(
+                        "{:?} is not a path segment",
+                        criterion
+                    ))),
+                };
+                Ok(segment)
+            })
+            .collect::<Result<_>>()?;
+        Ok(Path { start, segments })
+    }
+}
+
+/// Renders the expression of the path. Paths that `to_selector` rejects render to
+/// expressions that do not parse.
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Criteria(&self.criteria()))
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        Path::from_criteria(&parse(expression)?)
+    }
+}
+
+impl Expr {
+    /// The left side `@.name...` of a comparison, relative to the filtered value.
+    pub fn path_rel<I, S>(names: I) -> Comparison
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Expr::path(names.into_iter().fold(Path::current(), Path::child))
+    }
+
+    /// The left side `$.name...` of a comparison, from the root of the document.
+    pub fn path_abs<I, S>(names: I) -> Comparison
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Expr::path(names.into_iter().fold(Path::root(), Path::child))
+    }
+
+    /// Any path as the left side of a comparison.
+    pub fn path(path: Path) -> Comparison {
+        Comparison { path }
+    }
+
+    /// Both conditions hold.
+    pub fn and(self, other: Expr) -> Expr {
+        let mut conditions = match self {
+            Expr::And(conditions) => conditions,
+            condition => vec![condition],
+        };
+        match other {
+            Expr::And(more) => conditions.extend(more),
+            condition => conditions.push(condition),
+        }
+        Expr::And(conditions)
+    }
+
+    /// Either condition holds.
+    pub fn or(self, other: Expr) -> Expr {
+        let mut conditions = match self {
+            Expr::Or(conditions) => conditions,
+            condition => vec![condition],
+        };
+        match other {
+            Expr::Or(more) => conditions.extend(more),
+            condition => conditions.push(condition),
+        }
+        Expr::Or(conditions)
+    }
+
+    // Number of alternatives once `&&` is distributed over `||`, without distributing
+    fn count_alternatives(&self) -> usize {
+        match *self {
+            Expr::Compare(..) => 1,
+            Expr::Or(ref conditions) => conditions
+                .iter()
+                .map(Expr::count_alternatives)
+                .fold(0, usize::saturating_add),
+            Expr::And(ref conditions) => conditions
+                .iter()
+                .map(Expr::count_alternatives)
+                .fold(1, usize::saturating_mul),
+        }
+    }
+
+    // The comparisons of each alternative, `&&` distributed over `||`
+    fn alternatives(&self) -> Vec<Vec<(&Path, Operator, &Operand)>> {
+        match *self {
+            Expr::Compare(ref path, operator, ref operand) => vec![vec![(path, operator, operand)]],
+            Expr::Or(ref conditions) => conditions.iter().flat_map(Expr::alternatives).collect(),
+            Expr::And(ref conditions) => {
+                conditions
+                    .iter()
+                    .fold(vec![vec![]], |alternatives, condition| {
+                        let more = condition.alternatives();
+                        alternatives
+                            .iter()
+                            .flat_map(|left| {
+                                more.iter()
+                                    .map(move |right| left.iter().chain(right).cloned().collect())
+                            })
+                            .collect()
+                    })
+            }
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.count_alternatives() > MAX_ALTERNATIVES {
+            bail!(ErrorKind::Compile(format!(
+                "a filter can have at most {} alternatives once `&&` is distributed over `||`",
+                MAX_ALTERNATIVES
+            )));
+        }
+        let alternatives = self.alternatives();
+        if alternatives.is_empty() || alternatives.iter().any(Vec::is_empty) {
+            bail!(ErrorKind::Compile(
+                "a filter needs at least one comparison".to_owned()
+            ));
+        }
+        for &(path, _, operand) in alternatives.iter().flatten() {
+            path.check()?;
+            operand.check()?;
+        }
+        Ok(())
+    }
+
+    // Too many alternatives give no criteria, and render as `[?()]` which does not parse
+    fn criteria(&self) -> Vec<Criterion> {
+        let mut criteria = vec![];
+        if self.count_alternatives() > MAX_ALTERNATIVES {
+            return criteria;
+        }
+        for (i, alternative) in self.alternatives().into_iter().enumerate() {
+            if i > 0 {
+                criteria.push(Criterion::Or);
+            }
+            for (j, (path, operator, operand)) in alternative.into_iter().enumerate() {
+                if j > 0 {
+                    criteria.push(Criterion::And);
+                }
+                criteria.extend(path.criteria());
+                criteria.push(operator.to_criterion());
+                criteria.push(operand.criterion());
+            }
+        }
+        criteria
+    }
+
+    // `||` binds weaker than `&&`
+    fn from_criteria(criteria: &[Criterion]) -> Result<Self> {
+        let mut alternatives = vec![];
+        for alternative in criteria.split(|c| *c == Criterion::Or) {
+            let mut conditions = alternative
+                .split(|c| *c == Criterion::And)
+                .map(Expr::condition)
+                .collect::<Result<Vec<_>>>()?;
+            alternatives.push(match conditions.len() {
+                1 => conditions.remove(0),
+                _ => Expr::And(conditions),
+            });
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Expr::Or(alternatives),
+        })
+    }
+
+    fn condition(criteria: &[Criterion]) -> Result<Self> {
+        let position = criteria
+            .iter()
+            .position(|c| Operator::from_criterion(c).is_some());
+        match position.map(|position| criteria.split_at(position)) {
+            Some((path, [operator, operand])) => Ok(Expr::Compare(
+                Path::from_criteria(path)?,
+                Operator::from_criterion(operator).unwrap_or(Operator::Equal),
+                Operand::from_criterion(operand)?,
+            )),
+            _ => bail!(ErrorKind::Compile(
+                "a filter condition must be a comparison".to_owned()
+            )),
+        }
+    }
+}
+
+impl Comparison {
+    /// A comparison with any operator, e.g. one matched from another filter.
+    pub fn compare<O: Into<Operand>>(self, operator: Operator, operand: O) -> Expr {
+        Expr::Compare(self.path, operator, operand.into())
+    }
+
+    /// `==`
+    pub fn eq<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::Equal, operand)
+    }
+
+    /// `!=`
+    pub fn ne<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::Different, operand)
+    }
+
+    /// `>`
+    pub fn gt<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::Greater, operand)
+    }
+
+    /// `>=`
+    pub fn ge<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::GreaterOrEqual, operand)
+    }
+
+    /// `<`
+    pub fn lt<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::Lower, operand)
+    }
+
+    /// `<=`
+    pub fn le<O: Into<Operand>>(self, operand: O) -> Expr {
+        self.compare(Operator::LowerOrEqual, operand)
+    }
+
+    /// `=~ /pattern/`, the pattern in the syntax of the `regex` crate.
+    pub fn matches<S: Into<String>>(self, pattern: S) -> Expr {
+        self.compare(Operator::Matches, Operand::Regex(pattern.into()))
+    }
+}
+
+impl Operand {
+    fn check(&self) -> Result<()> {
+        match *self {
+            Operand::Number(value) if !value.is_finite() || value.is_sign_negative() => {
+                bail!(ErrorKind::Compile(format!(
+                    "numbers must be finite and not negative, found {}",
+                    value
+                )))
+            }
+            Operand::Array(ref items) => {
+                let numbers = items.iter().all(|item| matches!(*item, Operand::Number(_)));
+                let strings = items.iter().all(|item| matches!(*item, Operand::String(_)));
+                if items.is_empty() || !(numbers || strings) {
+                    bail!(ErrorKind::Compile(
+                        "array items must be all numbers or all strings".to_owned()
+                    ));
+                }
+                for item in items {
+                    item.check()?;
+                }
+            }
+            Operand::Path(ref path) => {
+                if path.start != Start::Root {
+                    bail!(ErrorKind::Compile(
+                        "a path compared against must start with `$`".to_owned()
+                    ));
+                }
+                path.check()?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn criterion(&self) -> Criterion {
+        match *self {
+            Operand::String(ref content) => Criterion::Literal(content.clone()),
+            Operand::Number(value) => Criterion::Float(value),
+            Operand::Array(ref items) => {
+                Criterion::Array(items.iter().map(Operand::criterion).collect())
+            }
+            Operand::Path(ref path) => Criterion::SubExpression(path.criteria()),
+            Operand::Regex(ref pattern) => Criterion::Regex(Pattern::new(pattern.clone())),
+        }
+    }
+
+    fn from_criterion(criterion: &Criterion) -> Result<Self> {
+        let operand = match *criterion {
+            Criterion::Literal(ref content) => Operand::String(content.clone()),
+            Criterion::Float(value) => Operand::Number(value),
+            Criterion::Array(ref items) => Operand::Array(
+                items
+                    .iter()
+                    .map(Operand::from_criterion)
+                    .collect::<Result<_>>()?,
+            ),
+            Criterion::SubExpression(ref criteria) => Operand::Path(Path::from_criteria(criteria)?),
+            Criterion::Regex(ref pattern) => Operand::Regex(pattern.source.clone()),
+            ref criterion => bail!(ErrorKind::Compile(format!(
+                "{:?} is not a comparison operand",
+                criterion
+            ))),
+        };
+        Ok(operand)
+    }
+}
+
+macro_rules! number_operand {
+    ($($number:ty),*) => {
+        $(
+            impl From<$number> for Operand {
+                fn from(value: $number) -> Self {
+                    Operand::Number(value as f64)
+                }
+            }
+        )*
+    };
+}
+
+number_operand!(f64, f32, i32, u32, i64, u64, usize);
+
+impl<'a> From<&'a str> for Operand {
+    fn from(content: &'a str) -> Self {
+        Operand::String(content.to_owned())
+    }
+}
+
+impl From<String> for Operand {
+    fn from(content: String) -> Self {
+        Operand::String(content)
+    }
+}
+
+impl From<Path> for Operand {
+    fn from(path: Path) -> Self {
+        Operand::Path(path)
+    }
+}
+
+impl<T: Into<Operand>> From<Vec<T>> for Operand {
+    fn from(items: Vec<T>) -> Self {
+        Operand::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slice_ranges() {
+        let slices = Path::root()
+            .slice(1..3)
+            .slice(1..=3)
+            .slice(..3)
+            .slice(..=3)
+            .slice(2..)
+            .slice(..)
+            .slice(3..3);
+        assert_eq!(slices.to_string(), "$[1:2][1:3][:3][0:3][2:][0:][:0]");
+    }
+
+    #[test]
+    fn test_and_distributes_over_or() {
+        let a = Expr::path_rel(["a"]).eq(1);
+        let b = Expr::path_rel(["b"]).eq(2);
+        let c = Expr::path_rel(["c"]).eq(3);
+        let path = Path::root().filter(a.or(b).and(c));
+        assert_eq!(
+            path.to_string(),
+            "$[?(@.a == 1) && ?(@.c == 3) || ?(@.b == 2) && ?(@.c == 3)]"
+        );
+    }
+
+    #[test]
+    fn test_alternatives_are_bounded() {
+        let either = || Expr::path_rel(["a"]).eq(1).or(Expr::path_rel(["b"]).eq(2));
+        let condition = (1..64).fold(either(), |condition, _| condition.and(either()));
+        assert_eq!(condition.count_alternatives(), usize::MAX);
+        assert_eq!(Path::root().filter(condition).to_string(), "$[?()]");
+        assert!(parse("$[?()]").is_err());
+    }
+
+    #[test]
+    fn test_criteria_round_trip() {
+        let criteria = parse("$..books[?(@.price < 10) && ?(@.tags == ['a', 'b'])]").unwrap();
+        let path = Path::from_criteria(&criteria).unwrap();
+        assert_eq!(path.criteria(), criteria);
+    }
+}
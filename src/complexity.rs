@@ -0,0 +1,192 @@
+use errors::*;
+use plan::{Expr, Operand, Plan, Segment};
+
+/// Static cost indicators of a selector, see `Selector::complexity`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Complexity {
+    /// Filters within filters: 1 for `$.a[?(@.b > 1)]`, 2 for
+    /// `$.a[?(@.b[?(@.c == 1)].d > 1)]`.
+    pub filter_depth: usize,
+
+    /// `..` segments, those of paths in filters included.
+    pub descendants: usize,
+
+    /// Paths in filters that start from `$`.
+    pub absolute_paths: usize,
+
+    /// Total length of the patterns of regular expressions, in bytes.
+    pub regex_size: usize,
+
+    /// Widest choice in a filter: the most `||` alternatives of a filter or items of an
+    /// array operand. 3 for `$[?(@.a == 1) || ?(@.b == ['x', 'y', 'z'])]`.
+    pub union_fan_out: usize,
+}
+
+/// Thresholds of `Complexity` above which `Selector::with_limits` rejects a selector.
+///
+/// Every threshold is optional, `ComplexityLimits::default()` has none.
+#[derive(Debug, Clone, Default)]
+pub struct ComplexityLimits {
+    pub max_filter_depth: Option<usize>,
+    pub max_descendants: Option<usize>,
+    pub max_absolute_paths: Option<usize>,
+    pub max_regex_size: Option<usize>,
+    pub max_union_fan_out: Option<usize>,
+}
+
+impl Complexity {
+    pub(crate) fn of(plan: &Plan) -> Self {
+        let mut complexity = Complexity {
+            filter_depth: plan.filter_depth(),
+            ..Complexity::default()
+        };
+        complexity.add_plan(plan);
+        complexity
+    }
+
+    fn add_plan(&mut self, plan: &Plan) {
+        for segment in &plan.segments {
+            match *segment {
+                Segment::Descendant => self.descendants += 1,
+                Segment::Filter(ref expr) => {
+                    let alternatives = match *expr {
+                        Expr::Or(ref exprs) => exprs.len(),
+                        _ => 1,
+                    };
+                    self.union_fan_out = self.union_fan_out.max(alternatives);
+                    self.add_expr(expr);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Paths of filters with a slot in the query cache are the absolute ones
+    fn add_path(&mut self, path: &Plan) {
+        if path.slot.is_some() {
+            self.absolute_paths += 1;
+        }
+        self.add_plan(path);
+    }
+
+    fn add_expr(&mut self, expr: &Expr) {
+        match *expr {
+            Expr::Or(ref exprs) | Expr::And(ref exprs) => {
+                for expr in exprs {
+                    self.add_expr(expr);
+                }
+            }
+            Expr::Exists(ref path) => self.add_path(path),
+            Expr::Compare(ref path, _, ref operand) => {
+                self.add_path(path);
+                self.add_operand(operand);
+            }
+        }
+    }
+
+    fn add_operand(&mut self, operand: &Operand) {
+        match *operand {
+            Operand::Array(ref items) => {
+                self.union_fan_out = self.union_fan_out.max(items.len());
+                for item in items {
+                    self.add_operand(item);
+                }
+            }
+            Operand::Path(ref path) => self.add_path(path),
+            Operand::Regex(ref regex) => self.regex_size += regex.as_str().len(),
+            Operand::String(_) | Operand::Number(_) => {}
+        }
+    }
+
+    // The first threshold exceeded, if any
+    pub(crate) fn check(&self, limits: &ComplexityLimits) -> Result<()> {
+        let checks = [
+            (self.filter_depth, limits.max_filter_depth, "filter depth"),
+            (
+                self.descendants,
+                limits.max_descendants,
+                "descendant segments",
+            ),
+            (
+                self.absolute_paths,
+                limits.max_absolute_paths,
+                "absolute paths in filters",
+            ),
+            (self.regex_size, limits.max_regex_size, "regex size"),
+            (
+                self.union_fan_out,
+                limits.max_union_fan_out,
+                "union fan-out",
+            ),
+        ];
+        for &(value, max, name) in &checks {
+            match max {
+                Some(max) if value > max => bail!(ErrorKind::TooComplex(format!(
+                    "{} is {}, at most {} allowed",
+                    name, value, max
+                ))),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse;
+    use plan::compile;
+
+    fn complexity(expression: &str) -> Complexity {
+        Complexity::of(&compile(&parse(expression).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn test_complexity() {
+        assert_eq!(complexity("$.a[0].b"), Complexity::default());
+        assert_eq!(
+            complexity("$..a..[0]"),
+            Complexity {
+                descendants: 2,
+                ..Complexity::default()
+            }
+        );
+        assert_eq!(
+            complexity("$..books[?(@.price > $..prices[?(@.title =~ /^moby/i)].price)]"),
+            Complexity {
+                filter_depth: 2,
+                descendants: 2,
+                absolute_paths: 1,
+                // `(?i)^moby`
+                regex_size: 9,
+                union_fan_out: 1,
+            }
+        );
+        assert_eq!(
+            complexity("$.a[?(@.b == $.c) || ?($.d == $.e)]"),
+            Complexity {
+                filter_depth: 1,
+                absolute_paths: 3,
+                union_fan_out: 2,
+                ..Complexity::default()
+            }
+        );
+        assert_eq!(
+            complexity("$.a[?(@.b == 1) || ?(@.c == ['x', 'y', 'z'])]"),
+            Complexity {
+                filter_depth: 1,
+                union_fan_out: 3,
+                ..Complexity::default()
+            }
+        );
+        assert_eq!(
+            complexity("$.a[?(@.b[?(@.c == 1) || ?(@.c == 2) || ?(@.d == 3)].e == 1)]"),
+            Complexity {
+                filter_depth: 2,
+                union_fan_out: 3,
+                ..Complexity::default()
+            }
+        );
+    }
+}
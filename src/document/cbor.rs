@@ -0,0 +1,134 @@
+use ciborium::value::Value;
+use serde_json;
+use std::borrow::Cow;
+use std::slice;
+
+use super::{Document, Key, Kind};
+
+/// Members of a `ciborium::Value::Map`.
+pub struct Members<'a> {
+    iter: slice::Iter<'a, (Value, Value)>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (to_key(key), value))
+    }
+}
+
+// Tags are ignored, `1(1363896240)` is looked up like `1363896240`
+fn untag(mut value: &Value) -> &Value {
+    while let Value::Tag(_, ref inner) = *value {
+        value = inner;
+    }
+    value
+}
+
+// Keys other than text, integers and byte strings are spelled as JSON
+fn to_key(key: &Value) -> Key<'_> {
+    match *untag(key) {
+        Value::Text(ref name) => Key::Name(name),
+        Value::Integer(integer) => Key::Integer(integer.into()),
+        Value::Bytes(ref bytes) => Key::Bytes(bytes),
+        ref other => Key::Other(serde_json::to_string(other).unwrap_or_default()),
+    }
+}
+
+fn is_key(key: &Value, wanted: &Key) -> bool {
+    match (untag(key), wanted) {
+        (&Value::Integer(integer), &Key::Integer(wanted)) => i128::from(integer) == wanted,
+        (Value::Bytes(bytes), &Key::Bytes(wanted)) => bytes.as_slice() == wanted,
+        _ => false,
+    }
+}
+
+impl Document for Value {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Value>;
+
+    fn kind(&self) -> Kind {
+        match *untag(self) {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Integer(_) | Value::Float(_) => Kind::Number,
+            Value::Text(_) => Kind::String,
+            Value::Bytes(_) => Kind::Bytes,
+            Value::Array(_) => Kind::Array,
+            Value::Map(_) => Kind::Object,
+            Value::Tag(..) => unreachable!("tags are looked through"),
+            _ => Kind::Null,
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *untag(self) {
+            Value::Map(ref map) => map.iter().find_map(|(key, value)| match *untag(key) {
+                Value::Text(ref key) if key == name => Some((key.as_str(), value)),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Self::Members<'_>> {
+        match *untag(self) {
+            Value::Map(ref map) => Some(Members { iter: map.iter() }),
+            _ => None,
+        }
+    }
+
+    fn member_by_key<'a>(&'a self, key: &Key) -> Option<(Key<'a>, &'a Self)> {
+        match *untag(self) {
+            Value::Map(ref map) => map
+                .iter()
+                .find(|entry| is_key(&entry.0, key))
+                .map(|(key, value)| (to_key(key), value)),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *untag(self) {
+            Value::Array(ref array) => array.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Self::Elements<'_>> {
+        match *untag(self) {
+            Value::Array(ref array) => Some(array.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *untag(self) {
+            Value::Array(ref array) => Some(array.len()),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        match *untag(self) {
+            Value::Text(ref text) => Some(Cow::Borrowed(text)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match *untag(self) {
+            Value::Integer(integer) => Some(i128::from(integer) as f64),
+            Value::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match *untag(self) {
+            Value::Bool(boolean) => Some(boolean),
+            _ => None,
+        }
+    }
+}
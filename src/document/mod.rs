@@ -0,0 +1,197 @@
+use serde_json::{map, Value};
+use std::borrow::Cow;
+use std::fmt;
+use std::slice;
+
+#[cfg(feature = "ciborium")]
+mod cbor;
+#[cfg(feature = "rmpv")]
+mod msgpack;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "serde_yaml")]
+mod yaml;
+
+/// A JSON-like tree that selectors can be evaluated on.
+///
+/// `Selector::find` and `Selector::find_strict` work on any implementation. A value is
+/// either a scalar, an array of values or an object mapping member names to values;
+/// `kind` tells which, and the other methods return `None` for values of another kind.
+///
+/// The iterators must yield the members and elements in document order, which is the order
+/// matches are found in.
+///
+/// Besides `serde_json::Value`, it is implemented for `serde_yaml::Value`, `toml::Value`,
+/// `ciborium::Value` and `rmpv::Value` with the features of the same names:
+///
+/// * YAML sequences and mappings are arrays and objects. Tagged values are looked through,
+///   `!Tag value` is `value`. Integer keys are matched by `[3]`, other keys that are not
+///   strings only by wildcards and descendants, see `Key`. Merge keys (`<<`) are not
+///   expanded.
+/// * TOML tables are objects and integers and floats numbers. Datetimes are strings spelled
+///   as in RFC 3339, e.g. `1979-05-27T07:32:00Z`.
+/// * CBOR and MessagePack maps are objects. Integer keys are matched by `[3]` and byte
+///   string keys by `[h'cafe']`. Byte strings, MessagePack extensions and MessagePack
+///   strings that are not UTF-8 are of kind `Bytes`. CBOR tags are looked through.
+pub trait Document {
+    type Members<'a>: Iterator<Item = (Key<'a>, &'a Self)>
+    where
+        Self: 'a;
+    type Elements<'a>: Iterator<Item = &'a Self>
+    where
+        Self: 'a;
+
+    fn kind(&self) -> Kind;
+
+    /// Looks up a member of an object by a string key, returning the key as stored in the
+    /// document.
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)>;
+
+    fn members(&self) -> Option<Self::Members<'_>>;
+
+    /// Looks up a member of an object by a `Key::Integer` or a `Key::Bytes`, for formats
+    /// whose objects have such keys. Documents with string keys only keep the default.
+    fn member_by_key<'a>(&'a self, _key: &Key) -> Option<(Key<'a>, &'a Self)> {
+        None
+    }
+
+    fn element(&self, index: usize) -> Option<&Self>;
+
+    fn elements(&self) -> Option<Self::Elements<'_>>;
+
+    /// Number of elements of an array.
+    fn array_len(&self) -> Option<usize>;
+
+    /// Contents of a string, which may be built for values that are only strings by
+    /// their spelling.
+    fn as_str(&self) -> Option<Cow<'_, str>>;
+
+    fn as_f64(&self) -> Option<f64>;
+
+    fn as_bool(&self) -> Option<bool>;
+}
+
+/// The kinds of JSON values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Bytes,
+    Array,
+    Object,
+}
+
+impl Kind {
+    /// Name of the kind, used in error messages.
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Null => "null",
+            Kind::Bool => "boolean",
+            Kind::Number => "number",
+            Kind::String => "string",
+            Kind::Bytes => "bytes",
+            Kind::Array => "array",
+            Kind::Object => "object",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Key of a member of an object.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Key<'a> {
+    /// A string, matched by named children such as `.name`.
+    Name(&'a str),
+    /// An integer, matched by indexes such as `[3]`.
+    Integer(i128),
+    /// A byte string, matched by `[h'cafe']`.
+    Bytes(&'a [u8]),
+    /// Any other key, spelled as in the source format. The member is only matched by
+    /// wildcards and descendants, and appears as `[spelling]` in normalized paths.
+    Other(String),
+}
+
+/// Members of a `serde_json::Map`.
+pub struct Members<'a> {
+    iter: map::Iter<'a>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (Key::Name(key), value))
+    }
+}
+
+impl Document for Value {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Value>;
+
+    fn kind(&self) -> Kind {
+        match *self {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Array(_) => Kind::Array,
+            Value::Object(_) => Kind::Object,
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *self {
+            Value::Object(ref map) => map
+                .get_key_value(name)
+                .map(|(key, value)| (key.as_str(), value)),
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Self::Members<'_>> {
+        match *self {
+            Value::Object(ref map) => Some(Members { iter: map.iter() }),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *self {
+            Value::Array(ref vec) => vec.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Self::Elements<'_>> {
+        match *self {
+            Value::Array(ref vec) => Some(vec.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *self {
+            Value::Array(ref vec) => Some(vec.len()),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        Value::as_str(self).map(Cow::Borrowed)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Value::as_f64(self)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        Value::as_bool(self)
+    }
+}
@@ -0,0 +1,130 @@
+use rmpv::{Integer, Value};
+use std::borrow::Cow;
+use std::slice;
+
+use super::{Document, Key, Kind};
+
+/// Members of a `rmpv::Value::Map`.
+pub struct Members<'a> {
+    iter: slice::Iter<'a, (Value, Value)>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (to_key(key), value))
+    }
+}
+
+fn to_integer(integer: &Integer) -> Option<i128> {
+    match integer.as_i64() {
+        Some(integer) => Some(integer.into()),
+        None => integer.as_u64().map(i128::from),
+    }
+}
+
+// Strings that are not UTF-8 are byte strings, keys other than strings, integers and
+// binaries are spelled the way `rmpv` displays them
+fn to_key(key: &Value) -> Key<'_> {
+    match *key {
+        Value::String(ref name) => match name.as_str() {
+            Some(name) => Key::Name(name),
+            None => Key::Bytes(name.as_bytes()),
+        },
+        Value::Integer(ref integer) => match to_integer(integer) {
+            Some(integer) => Key::Integer(integer),
+            None => Key::Other(key.to_string()),
+        },
+        Value::Binary(ref bytes) => Key::Bytes(bytes),
+        ref other => Key::Other(other.to_string()),
+    }
+}
+
+fn is_key(key: &Value, wanted: &Key) -> bool {
+    match (key, wanted) {
+        (Value::Integer(integer), &Key::Integer(wanted)) => to_integer(integer) == Some(wanted),
+        (Value::Binary(bytes), &Key::Bytes(wanted)) => bytes.as_slice() == wanted,
+        (Value::String(string), &Key::Bytes(wanted)) => {
+            string.is_err() && string.as_bytes() == wanted
+        }
+        _ => false,
+    }
+}
+
+impl Document for Value {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Value>;
+
+    fn kind(&self) -> Kind {
+        match *self {
+            Value::Nil => Kind::Null,
+            Value::Boolean(_) => Kind::Bool,
+            Value::Integer(_) | Value::F32(_) | Value::F64(_) => Kind::Number,
+            Value::String(ref string) if string.is_str() => Kind::String,
+            Value::String(_) | Value::Binary(_) | Value::Ext(..) => Kind::Bytes,
+            Value::Array(_) => Kind::Array,
+            Value::Map(_) => Kind::Object,
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *self {
+            Value::Map(ref map) => map.iter().find_map(|(key, value)| match key.as_str() {
+                Some(key) if key == name => Some((key, value)),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Self::Members<'_>> {
+        match *self {
+            Value::Map(ref map) => Some(Members { iter: map.iter() }),
+            _ => None,
+        }
+    }
+
+    fn member_by_key<'a>(&'a self, key: &Key) -> Option<(Key<'a>, &'a Self)> {
+        match *self {
+            Value::Map(ref map) => map
+                .iter()
+                .find(|entry| is_key(&entry.0, key))
+                .map(|(key, value)| (to_key(key), value)),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *self {
+            Value::Array(ref array) => array.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Self::Elements<'_>> {
+        match *self {
+            Value::Array(ref array) => Some(array.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *self {
+            Value::Array(ref array) => Some(array.len()),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        Value::as_str(self).map(Cow::Borrowed)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Value::as_f64(self)
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        Value::as_bool(self)
+    }
+}
@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+use std::slice;
+use toml::{map, Value};
+
+use super::{Document, Key, Kind};
+
+/// Members of a `toml::Table`.
+pub struct Members<'a> {
+    iter: map::Iter<'a>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (Key::Name(key), value))
+    }
+}
+
+impl Document for Value {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Value>;
+
+    fn kind(&self) -> Kind {
+        match *self {
+            Value::Boolean(_) => Kind::Bool,
+            Value::Integer(_) | Value::Float(_) => Kind::Number,
+            Value::String(_) | Value::Datetime(_) => Kind::String,
+            Value::Array(_) => Kind::Array,
+            Value::Table(_) => Kind::Object,
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *self {
+            Value::Table(ref table) => table
+                .get_key_value(name)
+                .map(|(key, value)| (key.as_str(), value)),
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Self::Members<'_>> {
+        match *self {
+            Value::Table(ref table) => Some(Members { iter: table.iter() }),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *self {
+            Value::Array(ref array) => array.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Self::Elements<'_>> {
+        match *self {
+            Value::Array(ref array) => Some(array.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *self {
+            Value::Array(ref array) => Some(array.len()),
+            _ => None,
+        }
+    }
+
+    // Datetimes are spelled as in RFC 3339
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        match *self {
+            Value::String(ref string) => Some(Cow::Borrowed(string)),
+            Value::Datetime(ref datetime) => Some(Cow::Owned(datetime.to_string())),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(integer) => Some(integer as f64),
+            Value::Float(float) => Some(float),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        Value::as_bool(self)
+    }
+}
@@ -0,0 +1,137 @@
+use serde_yaml::{self, mapping, Number, Value};
+use std::borrow::Cow;
+use std::slice;
+
+use super::{Document, Key, Kind};
+
+/// Members of a `serde_yaml::Mapping`.
+pub struct Members<'a> {
+    iter: mapping::Iter<'a>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (to_key(key), value))
+    }
+}
+
+// Tags are ignored, `!Tag value` is looked up like `value`
+fn untag(mut value: &Value) -> &Value {
+    while let Value::Tagged(ref tagged) = *value {
+        value = &tagged.value;
+    }
+    value
+}
+
+fn to_integer(number: &Number) -> Option<i128> {
+    match number.as_i64() {
+        Some(integer) => Some(integer.into()),
+        None => number.as_u64().map(i128::from),
+    }
+}
+
+// Keys other than strings and integers are spelled as in YAML
+fn to_key(key: &Value) -> Key<'_> {
+    let key = untag(key);
+    match *key {
+        Value::String(ref name) => return Key::Name(name),
+        Value::Number(ref number) => {
+            if let Some(integer) = to_integer(number) {
+                return Key::Integer(integer);
+            }
+        }
+        _ => {}
+    }
+    let spelling = serde_yaml::to_string(key).unwrap_or_default();
+    Key::Other(spelling.trim_end().to_owned())
+}
+
+impl Document for Value {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Value>;
+
+    fn kind(&self) -> Kind {
+        match *untag(self) {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Sequence(_) => Kind::Array,
+            Value::Mapping(_) => Kind::Object,
+            Value::Tagged(_) => unreachable!("tags are looked through"),
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *untag(self) {
+            Value::Mapping(ref mapping) => {
+                mapping.iter().find_map(|(key, value)| match *untag(key) {
+                    Value::String(ref key) if key == name => Some((key.as_str(), value)),
+                    _ => None,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Self::Members<'_>> {
+        match *untag(self) {
+            Value::Mapping(ref mapping) => Some(Members {
+                iter: mapping.iter(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn member_by_key<'a>(&'a self, key: &Key) -> Option<(Key<'a>, &'a Self)> {
+        let wanted = match *key {
+            Key::Integer(wanted) => wanted,
+            _ => return None,
+        };
+        match *untag(self) {
+            Value::Mapping(ref mapping) => mapping
+                .iter()
+                .find(|&(key, _)| match *untag(key) {
+                    Value::Number(ref number) => to_integer(number) == Some(wanted),
+                    _ => false,
+                })
+                .map(|(key, value)| (to_key(key), value)),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *untag(self) {
+            Value::Sequence(ref sequence) => sequence.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<Self::Elements<'_>> {
+        match *untag(self) {
+            Value::Sequence(ref sequence) => Some(sequence.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *untag(self) {
+            Value::Sequence(ref sequence) => Some(sequence.len()),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        untag(self).as_str().map(Cow::Borrowed)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        untag(self).as_f64()
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        untag(self).as_bool()
+    }
+}
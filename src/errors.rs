@@ -1,12 +1,238 @@
+// error_chain checks `has_error_description_deprecated`, which is set by its own build script.
+#![allow(unexpected_cfgs)]
+
+use std::fmt;
+
 error_chain! {
+    foreign_links {
+        // Kept for compatibility, the parser reports invalid indexes as `Parse` errors
+        ParseIntError(::std::num::ParseIntError);
+        Io(::std::io::Error);
+        Json(::serde_json::Error);
+    }
+
     errors {
-        Parse(msg: String) {
+        Parse(err: ParseError) {
             description("parse error")
-            display("{}", msg)
+            display("{}", err)
+        }
+
+        Compile(msg: String) {
+            description("invalid selector")
+            display("invalid selector: {}", msg)
+        }
+
+        TooComplex(msg: String) {
+            description("selector too complex")
+            display("selector too complex: {}", msg)
+        }
+
+        Unsupported(feature: String) {
+            description("unsupported feature")
+            display("{} are not supported", feature)
+        }
+
+        TypeMismatch(path: String, expected: String, found: String) {
+            description("type mismatch")
+            display("type mismatch at {}: expected {}, found {}", path, expected, found)
+        }
+
+        MissingMember(path: String, member: String) {
+            description("missing member")
+            display("missing member {} at {}", member, path)
+        }
+
+        NonSingular(path: String, count: usize) {
+            description("non-singular comparison operand")
+            display("comparison operand at {} yields {} values, expected one", path, count)
+        }
+
+        MissingField(field: String, path: String) {
+            description("missing field")
+            display("field `{}`: nothing found at `{}`", field, path)
+        }
+
+        AmbiguousField(field: String, path: String, count: usize) {
+            description("ambiguous field")
+            display("field `{}`: expected a single value at `{}`, found {}", field, path, count)
+        }
+
+        LimitExceeded(limit: String) {
+            description("evaluation limit exceeded")
+            display("evaluation limit exceeded: {}", limit)
+        }
+
+        InvalidField(field: String, path: String, msg: String) {
+            description("invalid field")
+            display("field `{}`: invalid value at `{}`: {}", field, path, msg)
         }
     }
+}
 
-    foreign_links {
-        ParseIntError(::std::num::ParseIntError);
+/// Describes where and why a JSONPath expression could not be parsed.
+///
+/// The `Display` implementation gives a one-line message, `render()` points at the
+/// problem with a caret:
+///
+/// ```text
+/// $.books[?(@.price <> 3)]
+///                    ^
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    expression: String,
+    offset: usize,
+    line: usize,
+    column: usize,
+    token: Option<String>,
+    expected: Vec<String>,
+}
+
+impl ParseError {
+    pub(crate) fn new(expression: &str, offset: usize, expected: Vec<String>) -> Self {
+        let mut offset = offset.min(expression.len());
+        while !expression.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let before = &expression[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = before[line_start..].chars().count() + 1;
+
+        let mut expected_dedup: Vec<String> = vec![];
+        for item in expected {
+            if !expected_dedup.contains(&item) {
+                expected_dedup.push(item);
+            }
+        }
+
+        Self {
+            expression: expression.to_owned(),
+            offset,
+            line,
+            column,
+            token: token_at(&expression[offset..]),
+            expected: expected_dedup,
+        }
+    }
+
+    /// The expression that failed to parse.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Byte offset of the problem in the expression.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Line of the problem, starting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Column of the problem in characters, starting from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The offending token, or `None` when the expression ended too early.
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    /// Human readable descriptions of what could have been at the offset.
+    pub fn expected(&self) -> &[String] {
+        &self.expected
+    }
+
+    /// Renders the offending line of the expression with a caret under the problem.
+    pub fn render(&self) -> String {
+        let line = self.expression.lines().nth(self.line - 1).unwrap_or("");
+        format!("{}\n{}^", line, " ".repeat(self.column - 1))
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.token {
+            Some(ref token) => write!(f, "unexpected `{}`", token)?,
+            None => write!(f, "unexpected end of expression")?,
+        }
+        write!(f, " at line {}, column {}", self.line, self.column)?;
+
+        if let Some((last, init)) = self.expected.split_last() {
+            write!(f, ", expected ")?;
+            if !init.is_empty() {
+                write!(f, "{} or ", init.join(", "))?;
+            }
+            write!(f, "{}", last)?;
+        }
+        Ok(())
+    }
+}
+
+// Takes a word or a single character from the beginning of the input
+fn token_at(input: &str) -> Option<String> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut chars = input.chars();
+    let first = chars.next()?;
+    if is_word(first) {
+        Some(input.chars().take_while(|c| is_word(*c)).collect())
+    } else {
+        Some(first.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_error() {
+        let err = ParseError::new(
+            "$.books[?(@.price <> 3)]",
+            19,
+            vec![
+                "a number".to_owned(),
+                "a number".to_owned(),
+                "a path".to_owned(),
+            ],
+        );
+        assert_eq!(err.line(), 1);
+        assert_eq!(err.column(), 20);
+        assert_eq!(err.token(), Some(">"));
+        assert_eq!(
+            err.expected(),
+            &["a number".to_owned(), "a path".to_owned()]
+        );
+        assert_eq!(
+            err.to_string(),
+            "unexpected `>` at line 1, column 20, expected a number or a path"
+        );
+        assert_eq!(
+            err.render(),
+            "$.books[?(@.price <> 3)]\n                   ^"
+        );
+    }
+
+    #[test]
+    fn test_parse_error_multiline() {
+        let err = ParseError::new("$.store\n.books!", 14, vec![]);
+        assert_eq!(err.line(), 2);
+        assert_eq!(err.column(), 7);
+        assert_eq!(err.render(), ".books!\n      ^");
+        assert_eq!(err.to_string(), "unexpected `!` at line 2, column 7");
+    }
+
+    #[test]
+    fn test_parse_error_end_of_expression() {
+        let err = ParseError::new("$.", 2, vec!["a member name".to_owned()]);
+        assert_eq!(err.token(), None);
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of expression at line 1, column 3, expected a member name"
+        );
     }
 }
@@ -0,0 +1,124 @@
+//! Support code for `#[derive(JsonPathExtract)]` from the `jsonpath_derive` crate.
+//!
+//! The derive generates an implementation of [`JsonPathExtract`](trait.JsonPathExtract.html)
+//! that runs one selector per field and converts the matches with the helpers below:
+//!
+//! * `Vec<T>` fields collect every match,
+//! * `Option<T>` fields accept zero or one match,
+//! * any other field requires exactly one match.
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use errors::*;
+use selector::Selector;
+
+/// A type that can be built from a JSON document by applying JSONPath selectors.
+pub trait JsonPathExtract: Sized {
+    fn extract(root: &Value) -> Result<Self>;
+}
+
+pub fn one<T: DeserializeOwned>(
+    selector: &Selector,
+    root: &Value,
+    field: &str,
+    path: &str,
+) -> Result<T> {
+    let found: Vec<&Value> = selector.find(root).collect();
+    match found.len() {
+        0 => Err(ErrorKind::MissingField(field.to_owned(), path.to_owned()).into()),
+        1 => convert(found[0], field, path),
+        count => Err(ErrorKind::AmbiguousField(field.to_owned(), path.to_owned(), count).into()),
+    }
+}
+
+pub fn optional<T: DeserializeOwned>(
+    selector: &Selector,
+    root: &Value,
+    field: &str,
+    path: &str,
+) -> Result<Option<T>> {
+    let found: Vec<&Value> = selector.find(root).collect();
+    match found.len() {
+        0 => Ok(None),
+        1 => convert(found[0], field, path).map(Some),
+        count => Err(ErrorKind::AmbiguousField(field.to_owned(), path.to_owned(), count).into()),
+    }
+}
+
+pub fn all<T: DeserializeOwned>(
+    selector: &Selector,
+    root: &Value,
+    field: &str,
+    path: &str,
+) -> Result<Vec<T>> {
+    selector
+        .find(root)
+        .map(|value| convert(value, field, path))
+        .collect()
+}
+
+fn convert<T: DeserializeOwned>(value: &Value, field: &str, path: &str) -> Result<T> {
+    serde_json::from_value(value.clone()).map_err(|e| {
+        ErrorKind::InvalidField(field.to_owned(), path.to_owned(), e.to_string()).into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc() -> Value {
+        serde_json::from_str(r#"{"price": 19.95, "tags": ["a", "b"], "title": "Le mur"}"#).unwrap()
+    }
+
+    #[test]
+    fn test_one() {
+        let selector = Selector::new("$.price").unwrap();
+        let price: f64 = one(&selector, &doc(), "price", "$.price").unwrap();
+        assert_eq!(price, 19.95);
+    }
+
+    #[test]
+    fn test_one_missing() {
+        let selector = Selector::new("$.isbn").unwrap();
+        let err = one::<String>(&selector, &doc(), "isbn", "$.isbn").unwrap_err();
+        assert_eq!(err.to_string(), "field `isbn`: nothing found at `$.isbn`");
+    }
+
+    #[test]
+    fn test_one_ambiguous() {
+        let selector = Selector::new("$.tags.*").unwrap();
+        let err = one::<String>(&selector, &doc(), "tag", "$.tags.*").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "field `tag`: expected a single value at `$.tags.*`, found 2"
+        );
+    }
+
+    #[test]
+    fn test_one_invalid() {
+        let selector = Selector::new("$.title").unwrap();
+        let err = one::<f64>(&selector, &doc(), "title", "$.title").unwrap_err();
+        match *err.kind() {
+            ErrorKind::InvalidField(ref field, ref path, _) => {
+                assert_eq!(field, "title");
+                assert_eq!(path, "$.title");
+            }
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_optional() {
+        let selector = Selector::new("$.isbn").unwrap();
+        let isbn: Option<String> = optional(&selector, &doc(), "isbn", "$.isbn").unwrap();
+        assert_eq!(isbn, None);
+    }
+
+    #[test]
+    fn test_all() {
+        let selector = Selector::new("$.tags.*").unwrap();
+        let tags: Vec<String> = all(&selector, &doc(), "tags", "$.tags.*").unwrap();
+        assert_eq!(tags, vec!["a", "b"]);
+    }
+}
@@ -1,156 +1,117 @@
-use iter::Iter;
-use serde_json::Value;
-use std::f64::EPSILON;
-use structs::{Criterion, StackItem};
+use super::{select, select_strict, SharedCache};
+use document::{Document, Kind};
+use errors::*;
+use plan::{Operand, Operator};
 
-pub fn filter<'a>(
-    pattern: &Criterion,
-    value: &Criterion,
-    values: &[&Value],
-    root: &StackItem<'a>,
-) -> Option<bool> {
-    match *pattern {
-        Criterion::Equal => is_equal(value, values, root),
-        Criterion::Different => is_different(value, values, root),
-        Criterion::Lower => is_lower(value, values, root),
-        Criterion::LowerOrEqual => is_lower_or_equal(value, values, root),
-        Criterion::Greater => is_greater(value, values, root),
-        Criterion::GreaterOrEqual => is_greater_or_equal(value, values, root),
-        _ => None,
+// Every value found by the left side of the comparison must satisfy it
+pub fn compare<'a, D: Document>(
+    operator: Operator,
+    values: &[&D],
+    operand: &Operand,
+    root: &'a D,
+    cache: &SharedCache<'a, D>,
+) -> bool {
+    match *operand {
+        Operand::String(ref content) => values.iter().all(|value| match value.as_str() {
+            Some(string) => operator.apply(&*string, content.as_str()),
+            None => false,
+        }),
+        Operand::Number(number) => values.iter().all(|value| match value.as_f64() {
+            Some(value_number) => operator.apply(&value_number, &number),
+            None => false,
+        }),
+        Operand::Array(ref items) => items
+            .iter()
+            .any(|item| compare(operator, values, item, root, cache)),
+        Operand::Path(ref path) => select(path, root, root, cache).iter().all(|item| {
+            values
+                .iter()
+                .all(|value| compare_values(operator, *value, *item))
+        }),
+        Operand::Regex(ref regex) => values.iter().all(|value| match value.as_str() {
+            Some(string) => regex.is_match(&string),
+            None => false,
+        }),
     }
 }
 
-macro_rules! compare {
-    ($criterion:expr, $values:expr, $root:expr, $operator:tt,
-     $number_operator:tt, $eplison:expr, $absolute:expr, $method:tt) => (
-        match *$criterion {
-            Criterion::Literal(ref content) => {
-                for v in $values.iter() {
-                    if let Value::String(ref string_content) = **v {
-                        if string_content $operator content {
-                            return Some(false);
-                        }
-                    } else {
-                        return Some(false);
-                    }
-                }
-                Some(true)
+// Numbers found by two paths are compared with a tolerance of `f64::EPSILON`
+fn compare_values<D: Document>(operator: Operator, value: &D, item: &D) -> bool {
+    match (value.kind(), item.kind()) {
+        (Kind::Number, Kind::Number) => match (value.as_f64(), item.as_f64()) {
+            (Some(value_number), Some(item_number)) => {
+                compare_difference(operator, value_number - item_number)
             }
-
-            Criterion::Float(ref content) => {
-                for v in $values.iter() {
-                    if let Value::Number(ref number_content) = **v {
-                        if number_content.as_f64() $operator Some(*content) {
-                            return Some(false);
-                        }
-                    } else {
-                        return Some(false);
-                    }
-                }
-                Some(true)
-            }
-            Criterion::Array(ref content) => {
-                for item in content {
-                    if let Some(true) = $method(item, $values, &$root) {
-                        return Some(true);
-                    }
-                }
-                Some(false)
-            }
-            Criterion::SubExpression(ref expression) => {
-                validate_sub_expresion!($values, $root,
-                    $operator, $number_operator, $eplison, $absolute, expression)
-            }
-            _ => None,
-        }
-    )
-}
-
-macro_rules! validate_sub_expresion {
-    ($values:expr, $root:expr, $operator:tt, $number_operator:tt,
-     $eplison:expr, $absolute:expr, $expression:expr) => ({
-        let found: Vec<&Value> = Iter::new($root.item.value, &$expression).collect();
-
-        for item in &found {
-            for value in $values.iter() {
-                match (*value, *item) {
-                    (&Value::Number(ref value_content), &Value::Number(ref item_content)) => {
-                        match (value_content.as_f64(), item_content.as_f64()) {
-                            (Some(value_number), Some(item_number)) => {
-                                if !$absolute &&
-                                    (value_number - item_number) $number_operator $eplison {
-                                    return Some(false);
-                                }
-                                if $absolute &&
-                                    (value_number - item_number).abs() $number_operator $eplison {
-                                    return Some(false);
-                                }
-                            }
-                            _ => {
-                                return Some(false);
-                            }
-                        }
-                    }
-                    (&Value::String(ref value_content), &Value::String(ref item_content)) => {
-                        if value_content $operator item_content {
-                            return Some(false);
-                        }
-                    }
-                    (&Value::String(ref value_content), &Value::Array(ref items)) => {
-                        let mut returned = false;
-                        for item in items {
-                            if let Value::String(ref item_content) = *item {
-                                if !(value_content $operator item_content) {
-                                    returned = true;
-                                }
-                            }
-                        }
-                        if !returned {
-                            return Some(false)
-                        }
-                    }
-                    _ => {
-                        return Some(false);
-                    }
-                }
+            _ => false,
+        },
+        (Kind::String, Kind::String) => match (value.as_str(), item.as_str()) {
+            (Some(value_string), Some(item_string)) => {
+                operator.apply(&*value_string, &*item_string)
             }
-        }
-        Some(true)
-    })
-}
-
-fn is_equal<'a>(criterion: &Criterion, values: &[&Value], root: &StackItem<'a>) -> Option<bool> {
-    compare!(criterion, values, root, !=, >, EPSILON, true, is_equal)
-}
-
-fn is_different<'a>(
-    criterion: &Criterion,
-    values: &[&Value],
-    root: &StackItem<'a>,
-) -> Option<bool> {
-    compare!(criterion, values, root, ==, <, EPSILON, true, is_different)
+            _ => false,
+        },
+        (Kind::String, Kind::Array) => match (value.as_str(), item.elements()) {
+            (Some(value_string), Some(mut items)) => items.any(|item| match item.as_str() {
+                Some(item_string) => operator.apply(&*value_string, &*item_string),
+                None => false,
+            }),
+            _ => false,
+        },
+        _ => false,
+    }
 }
 
-fn is_lower<'a>(criterion: &Criterion, values: &[&Value], root: &StackItem<'a>) -> Option<bool> {
-    compare!(criterion, values, root, >=, >=, -EPSILON, false, is_lower)
+fn compare_difference(operator: Operator, difference: f64) -> bool {
+    match operator {
+        Operator::Equal => difference.abs() <= f64::EPSILON,
+        Operator::Different => difference.abs() >= f64::EPSILON,
+        Operator::Greater => difference >= f64::EPSILON,
+        Operator::GreaterOrEqual => difference >= -f64::EPSILON,
+        Operator::Lower => difference < -f64::EPSILON,
+        Operator::LowerOrEqual => difference <= f64::EPSILON,
+        Operator::Matches => false,
+    }
 }
 
-fn is_lower_or_equal<'a>(
-    value: &Criterion,
-    values: &[&Value],
-    root: &StackItem<'a>,
-) -> Option<bool> {
-    compare!(value, values, root, >, >, EPSILON, false, is_lower_or_equal)
-}
+// Strict mode: the compared operand must be a single value of the type of `operand`.
+// `path` is the normalized path of the filtered item.
+pub fn check<'a, D: Document>(
+    operand: &Operand,
+    values: &[&D],
+    root: &'a D,
+    path: &str,
+    cache: &SharedCache<'a, D>,
+) -> Result<()> {
+    if values.len() != 1 {
+        bail!(ErrorKind::NonSingular(path.to_owned(), values.len()));
+    }
+    let found = values[0];
 
-fn is_greater<'a>(criterion: &Criterion, values: &[&Value], root: &StackItem<'a>) -> Option<bool> {
-    compare!(criterion, values, root, <=, <, EPSILON, false, is_greater)
-}
+    let expected = match *operand {
+        Operand::String(_) | Operand::Regex(_) => "string",
+        Operand::Number(_) => "number",
+        Operand::Array(ref items) => {
+            for item in items {
+                check(item, values, root, path, cache)?;
+            }
+            return Ok(());
+        }
+        Operand::Path(ref path_plan) => {
+            let others = select_strict(path_plan, root, root, "$", cache)?;
+            if others.len() != 1 {
+                bail!(ErrorKind::NonSingular(path.to_owned(), others.len()));
+            }
+            others[0].kind().name()
+        }
+    };
 
-fn is_greater_or_equal<'a>(
-    criterion: &Criterion,
-    values: &[&Value],
-    root: &StackItem<'a>,
-) -> Option<bool> {
-    compare!(criterion, values, root, <, <, -EPSILON, false, is_greater_or_equal)
+    if found.kind().name() != expected {
+        let found = found.kind().name().to_owned();
+        bail!(ErrorKind::TypeMismatch(
+            path.to_owned(),
+            expected.to_owned(),
+            found
+        ));
+    }
+    Ok(())
 }
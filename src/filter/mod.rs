@@ -1,123 +1,151 @@
-use iter::Iter;
-use serde_json::Value;
-use structs::{Criterion, StackItem};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use document::Document;
+use errors::*;
+use iter::{Iter, StrictIter};
+use limits::Budget;
+use plan::{Expr, Plan};
 
 mod comparison;
 
-pub fn process_filter<'a>(stack: &mut StackItem, path: &[Criterion], root: &StackItem<'a>) -> bool {
-    let mut or_indexes = vec![];
-    let mut and_indexes = vec![];
+/// Results of the absolute paths in filters, shared by all iterators of a query.
+///
+/// Strict results are only cached on success, so that every filtered item reports
+/// the error.
+pub struct Cache<'a, D: 'a> {
+    lenient: HashMap<usize, Rc<Vec<&'a D>>>,
+    strict: HashMap<usize, Rc<Vec<&'a D>>>,
+    // Limits of the query, counted across all its iterators
+    pub budget: Option<Budget>,
+}
 
-    for (index, criterion) in path.iter().enumerate() {
-        match criterion {
-            Criterion::Or => or_indexes.push(index),
-            Criterion::And => and_indexes.push(index),
-            _ => {}
+impl<'a, D> Default for Cache<'a, D> {
+    fn default() -> Self {
+        Cache {
+            lenient: HashMap::new(),
+            strict: HashMap::new(),
+            budget: None,
         }
     }
+}
 
-    for (index, i) in or_indexes.iter().enumerate() {
-        let (left, right) = path.split_at(*i);
-
-        if index != or_indexes.len() - 1 {
-            if process_filter(stack, left, root) {
-                return true;
-            }
-        } else {
-            let mut right_vec = right.to_vec();
-            right_vec.remove(0);
-
-            if process_filter(stack, left, root) || process_filter(stack, &right_vec, root) {
-                return true;
+pub type SharedCache<'a, D> = Rc<RefCell<Cache<'a, D>>>;
+
+// With `strict_path`, the normalized path of the filtered item, operands of comparisons
+// must resolve to a single value of the type the comparison expects.
+pub fn matches<'a, D: Document>(
+    expr: &Expr,
+    value: &'a D,
+    root: &'a D,
+    strict_path: Option<&str>,
+    cache: &SharedCache<'a, D>,
+) -> Result<bool> {
+    match *expr {
+        Expr::Or(ref exprs) => {
+            for expr in exprs {
+                if matches(expr, value, root, strict_path, cache)? {
+                    return Ok(true);
+                }
             }
+            Ok(false)
         }
-    }
-
-    if or_indexes.is_empty() && !and_indexes.is_empty() {
-        for (index, i) in and_indexes.iter().enumerate() {
-            let (left, right) = path.split_at(*i);
-
-            if index != and_indexes.len() - 1 {
-                if !process_filter(stack, left, root) {
-                    return false;
-                }
-            } else {
-                let mut right_vec = right.to_vec();
-                right_vec.remove(0);
-
-                if !process_filter(stack, left, root) || !process_filter(stack, &right_vec, root) {
-                    return false;
+        Expr::And(ref exprs) => {
+            for expr in exprs {
+                if !matches(expr, value, root, strict_path, cache)? {
+                    return Ok(false);
                 }
             }
+            Ok(true)
         }
-        return true;
+        Expr::Exists(ref path) => Ok(!select(path, value, root, cache).is_empty()),
+        Expr::Compare(ref path, operator, ref operand) => match strict_path {
+            Some(strict_path) => {
+                let found = select_strict(path, value, root, strict_path, cache)?;
+                comparison::check(operand, &found, root, strict_path, cache)?;
+                Ok(comparison::compare(operator, &found, operand, root, cache))
+            }
+            None => {
+                let found = select(path, value, root, cache);
+                Ok(comparison::compare(operator, &found, operand, root, cache))
+            }
+        },
     }
+}
 
-    if !(or_indexes.is_empty() && and_indexes.is_empty()) {
-        return false;
+// Relative paths start from the filtered item, absolute ones (those with a slot) from the
+// root of the document
+pub fn select<'a, D: Document>(
+    path: &Plan,
+    value: &'a D,
+    root: &'a D,
+    cache: &SharedCache<'a, D>,
+) -> Rc<Vec<&'a D>> {
+    let slot = match path.slot {
+        Some(slot) => slot,
+        None => return Rc::new(Iter::with_cache(value, &path.segments, cache.clone()).collect()),
+    };
+
+    if let Some(found) = cache.borrow().lenient.get(&slot) {
+        return found.clone();
     }
+    let found = Rc::new(Iter::with_cache(root, &path.segments, cache.clone()).collect::<Vec<&D>>());
+    cache.borrow_mut().lenient.insert(slot, found.clone());
+    found
+}
 
-    let mut iterator = path.iter();
-    match iterator.next() {
-        Some(&Criterion::Element) => {
-            let found_condition = path.iter().position(|x| {
-                x == &Criterion::Equal
-                    || x == &Criterion::Different
-                    || x == &Criterion::Greater
-                    || x == &Criterion::GreaterOrEqual
-                    || x == &Criterion::Lower
-                    || x == &Criterion::LowerOrEqual
-            });
-
-            let (sub_path, condition) = match found_condition {
-                Some(index) => {
-                    let (left, right) = path.split_at(index);
-                    (left[1..].to_vec(), right.to_vec())
-                }
-                None => (path.to_owned(), vec![]),
-            };
-
-            let mut full_criterion = vec![Criterion::Root];
-            full_criterion.extend_from_slice(&sub_path);
-
-            let found: Vec<&Value> = Iter::new(stack.item.value, &full_criterion).collect();
-
-            match condition.len() {
-                0 => !found.is_empty(),
-                2 => match comparison::filter(&condition[0], &condition[1], &found, root) {
-                    Some(value) => value,
-                    None => false,
-                },
-                _ => false,
-            }
+// `strict_path` is the normalized path of the filtered item, absolute paths are reported
+// from `$`
+pub fn select_strict<'a, D: Document>(
+    path: &Plan,
+    value: &'a D,
+    root: &'a D,
+    strict_path: &str,
+    cache: &SharedCache<'a, D>,
+) -> Result<Rc<Vec<&'a D>>> {
+    let slot = match path.slot {
+        Some(slot) => slot,
+        None => {
+            let found = StrictIter::with_cache(value, &path.segments, strict_path, cache.clone())
+                .collect::<Result<Vec<&D>>>()?;
+            return Ok(Rc::new(found));
         }
-        Some(&Criterion::Root) => {
-            let found = path.iter().position(|x| {
-                x == &Criterion::Equal
-                    || x == &Criterion::Different
-                    || x == &Criterion::Greater
-                    || x == &Criterion::Lower
-            });
-
-            let (sub_path, condition) = match found {
-                Some(index) => {
-                    let (left, right) = path.split_at(index);
-                    (left.to_vec(), right.to_vec())
-                }
-                None => (path.to_owned(), vec![]),
-            };
+    };
 
-            let found: Vec<&Value> = Iter::new(root.item.value, &sub_path).collect();
+    if let Some(found) = cache.borrow().strict.get(&slot) {
+        return Ok(found.clone());
+    }
+    let found = StrictIter::with_cache(root, &path.segments, "$", cache.clone())
+        .collect::<Result<Vec<&D>>>()?;
+    let found = Rc::new(found);
+    cache.borrow_mut().strict.insert(slot, found.clone());
+    Ok(found)
+}
 
-            match condition.len() {
-                0 => !found.is_empty(),
-                2 => match comparison::filter(&condition[0], &condition[1], &found, root) {
-                    Some(value) => value,
-                    None => false,
-                },
-                _ => false,
-            }
-        }
-        _ => false,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse;
+    use plan::compile;
+    use serde_json::{self, Value};
+
+    #[test]
+    fn test_absolute_paths_are_cached() {
+        let json = r#"{"limit": 2, "items": [{"value": 1}, {"value": 2}, {"value": 3}]}"#;
+        let root: Value = serde_json::from_str(json).unwrap();
+        let plan = compile(
+            &parse("$.items[?(@.value >= $.items[?(@.value == $.limit)].value)].value").unwrap(),
+        )
+        .unwrap();
+
+        let cache = SharedCache::default();
+        let found: Vec<&Value> = Iter::with_cache(&root, &plan.segments, cache.clone()).collect();
+        assert_eq!(found, vec![2, 3]);
+
+        // `$.items[?(...)].value` and the nested `$.limit`
+        let cache = cache.borrow();
+        assert_eq!(cache.lenient.len(), 2);
+        assert!(cache.strict.is_empty());
     }
 }
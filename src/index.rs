@@ -0,0 +1,236 @@
+use serde_json::Value;
+use std::collections::HashMap;
+
+use document::Document;
+use structs::Step;
+
+/// A document along with lookup tables built once for many queries.
+///
+/// Every node of the document is numbered in document order, with a link to its parent,
+/// and the members of every object are listed by name. `Selector::find` accepts an
+/// `IndexedDocument` in place of the document: `..name` is then looked up instead of
+/// walking the whole tree. Parents, ancestors and normalized paths of the nodes found can
+/// be looked up too.
+///
+/// ```
+/// # extern crate jsonpath;
+/// # #[macro_use] extern crate serde_json;
+/// # fn main() {
+/// use jsonpath::{IndexedDocument, Selector};
+///
+/// let catalog = json!({"items": [{"sku": "a-1"}, {"sku": "b-2", "parts": [{"sku": "c-3"}]}]});
+/// let index = IndexedDocument::new(&catalog);
+///
+/// let selector = Selector::new("$..sku").unwrap();
+/// let skus: Vec<_> = selector.find(&index).collect();
+/// assert_eq!(skus, vec!["a-1", "b-2", "c-3"]);
+///
+/// let part = index.parent(skus[2]).unwrap();
+/// assert_eq!(index.path(part).unwrap(), "$['items'][1]['parts'][0]");
+/// assert_eq!(index.ancestors(part).count(), 4);
+/// # }
+/// ```
+pub struct IndexedDocument<'a, D: 'a = Value> {
+    root: &'a D,
+    // In document order, the root first
+    nodes: Vec<Node<'a, D>>,
+    // Node of every value, by address
+    ids: HashMap<usize, usize>,
+    // Members with a string key, ordered by their parent
+    names: HashMap<&'a str, Vec<usize>>,
+}
+
+struct Node<'a, D: 'a> {
+    value: &'a D,
+    parent: Option<usize>,
+    step: Step<'a>,
+    // One past the last descendant
+    end: usize,
+}
+
+/// What a selector can be evaluated on: a document, or an `IndexedDocument` of one.
+pub trait Searchable<'a> {
+    type Document: Document + 'a;
+
+    fn document(&self) -> &'a Self::Document;
+
+    fn index(&self) -> Option<&'a IndexedDocument<'a, Self::Document>>;
+}
+
+impl<'a, D: Document> Searchable<'a> for &'a D {
+    type Document = D;
+
+    fn document(&self) -> &'a D {
+        self
+    }
+
+    fn index(&self) -> Option<&'a IndexedDocument<'a, D>> {
+        None
+    }
+}
+
+impl<'a, D: Document> Searchable<'a> for &'a IndexedDocument<'a, D> {
+    type Document = D;
+
+    fn document(&self) -> &'a D {
+        self.root
+    }
+
+    fn index(&self) -> Option<&'a IndexedDocument<'a, D>> {
+        Some(self)
+    }
+}
+
+fn address<D>(value: &D) -> usize {
+    value as *const D as usize
+}
+
+impl<'a, D: Document> IndexedDocument<'a, D> {
+    pub fn new(root: &'a D) -> Self {
+        let mut index = IndexedDocument {
+            root,
+            nodes: vec![],
+            ids: HashMap::new(),
+            names: HashMap::new(),
+        };
+
+        // Walked with a stack of the nodes whose descendants are being numbered, so
+        // that deep documents do not need deep recursion
+        let mut stack: Vec<(usize, Vec<(&'a D, Step<'a>)>)> = vec![];
+        index.push(root, None, Step::Root, &mut stack);
+        while let Some(&mut (id, ref mut children)) = stack.last_mut() {
+            match children.pop() {
+                Some((child, step)) => index.push(child, Some(id), step, &mut stack),
+                None => {
+                    index.nodes[id].end = index.nodes.len();
+                    stack.pop();
+                }
+            }
+        }
+
+        // A parent has a single member of each name, the first one as `Document::member`
+        let nodes = &index.nodes;
+        for ids in index.names.values_mut() {
+            ids.sort_by_key(|&id| nodes[id].parent);
+            ids.dedup_by_key(|&mut id| nodes[id].parent);
+        }
+        index
+    }
+
+    fn push(
+        &mut self,
+        value: &'a D,
+        parent: Option<usize>,
+        step: Step<'a>,
+        stack: &mut Vec<(usize, Vec<(&'a D, Step<'a>)>)>,
+    ) {
+        let id = self.nodes.len();
+        if let Step::Key(name) = step {
+            self.names.entry(name).or_default().push(id);
+        }
+        self.ids.insert(address(value), id);
+        self.nodes.push(Node {
+            value,
+            parent,
+            step,
+            end: id + 1,
+        });
+
+        // Reversed, so that popping them gives the document order
+        let mut children: Vec<(&'a D, Step<'a>)> = match value.members() {
+            Some(members) => members.map(|(key, child)| (child, key.into())).collect(),
+            None => match value.elements() {
+                Some(elements) => elements
+                    .enumerate()
+                    .map(|(index, child)| (child, Step::Index(index)))
+                    .collect(),
+                None => vec![],
+            },
+        };
+        if !children.is_empty() {
+            children.reverse();
+            stack.push((id, children));
+        }
+    }
+
+    pub fn root(&self) -> &'a D {
+        self.root
+    }
+
+    /// Number of nodes in the document.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn id(&self, value: &D) -> Option<usize> {
+        self.ids.get(&address(value)).cloned()
+    }
+
+    /// The value containing `value`, `None` for the root and for values that are not
+    /// part of the document.
+    pub fn parent(&self, value: &D) -> Option<&'a D> {
+        let parent = self.nodes[self.id(value)?].parent?;
+        Some(self.nodes[parent].value)
+    }
+
+    /// The values containing `value`, from its parent up to the root.
+    pub fn ancestors(&self, value: &D) -> impl Iterator<Item = &'a D> + '_ {
+        let mut id = self.id(value);
+        ::std::iter::from_fn(move || {
+            id = self.nodes[id?].parent;
+            id.map(|id| self.nodes[id].value)
+        })
+    }
+
+    /// Normalized path of `value`, e.g. `$['store']['books'][0]`.
+    pub fn path(&self, value: &D) -> Option<String> {
+        let mut steps = vec![];
+        let mut id = Some(self.id(value)?);
+        while let Some(node) = id.map(|id| &self.nodes[id]) {
+            steps.push(&node.step);
+            id = node.parent;
+        }
+        Some(steps.iter().rev().map(|step| step.to_string()).collect())
+    }
+
+    /// The members named `name` of `value` and of all its descendants, in the order
+    /// `..name` finds them. `None` if `value` is not part of the document.
+    pub(crate) fn descendants_named(&self, value: &D, name: &str) -> Option<Vec<&'a D>> {
+        let start = self.id(value)?;
+        let end = self.nodes[start].end;
+        let ids = match self.names.get(name) {
+            Some(ids) => ids,
+            None => return Some(vec![]),
+        };
+        let parent = |id: &usize| self.nodes[*id].parent.unwrap_or(0);
+        let first = ids.partition_point(|id| parent(id) < start);
+        let last = ids.partition_point(|id| parent(id) < end);
+        Some(
+            ids[first..last]
+                .iter()
+                .map(|&id| self.nodes[id].value)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn test_nodes_are_numbered_in_document_order() {
+        let root: Value = serde_json::from_str(r#"{"a": {"b": [1, 2]}, "c": 3}"#).unwrap();
+        let index = IndexedDocument::new(&root);
+
+        let paths: Vec<String> = index
+            .nodes
+            .iter()
+            .map(|node| node.step.to_string())
+            .collect();
+        assert_eq!(paths, vec!["$", "['a']", "['b']", "[0]", "[1]", "['c']"]);
+        let ends: Vec<usize> = index.nodes.iter().map(|node| node.end).collect();
+        assert_eq!(ends, vec![6, 5, 5, 4, 5, 6]);
+    }
+}
@@ -1,79 +1,448 @@
+use document::{Document, Key};
+use errors::*;
+use filter::{self, SharedCache};
+use index::IndexedDocument;
+use limits::{Budget, EvalLimits};
+use plan::Segment;
 use serde_json::Value;
-use structs::{matches, Criterion, Item, StackItem, Step};
-
-pub struct Iter<'a, 'b> {
-    criteria: &'b [Criterion],
-    ci: usize,
-    current: Option<StackItem<'a>>,
-    root: StackItem<'a>,
-    stack: Vec<StackItem<'a>>,
+use std::collections::HashSet;
+use std::iter::Enumerate;
+use structs::{check_shape, Step};
+
+/// Iterator returned by `Selector::find`.
+///
+/// The segments of the plan are applied depth-first: every segment produces the candidates
+/// for the next one from the values matched so far. Named children, indexes and slices are looked
+/// up directly, only wildcards and filters visit every child.
+///
+/// Matches come in a defined order:
+///
+/// * the matches of a segment are in the order of the values they were found from, e.g.
+///   `$.*.a` yields the `a` of the first child before the `a` of the second,
+/// * wildcards and filters yield the elements of arrays by index, and the members of objects
+///   in the order of `Document::members`. For a `serde_json::Value` it is the order of its
+///   `Map`: sorted by name, or the order of the document with the `preserve_order` feature
+///   (or serde_json's own `preserve_order` enabled by another crate),
+/// * `..` applies the next segment to the value, then to each of its descendants in
+///   document order: `$..a` yields the `a` of the root, then the ones below its first child,
+///   and so on.
+///
+/// These rules cover `..`, wildcards and filters: the grammar has no unions such as
+/// `$['a','b']` or `$[0,1]`, so there is no order across union branches to define.
+///
+/// A value reached along several paths, e.g. by `$..*..*`, is yielded once per path, see
+/// `unique`.
+pub struct Iter<'a, 'b, D: 'a + Document = Value> {
+    segments: &'b [Segment],
+    root: &'a D,
+    stack: Vec<Frame<'a, D>>,
+    // Steps from the root to the value whose children are on top of the stack
+    steps: Vec<Step<'a>>,
+    // Normalized path of the value the iteration starts from, present in strict mode
+    strict_base: Option<String>,
+    // Results of absolute paths in filters, shared with the iterators evaluating filters
+    cache: SharedCache<'a, D>,
+    // Looks up `..name` instead of visiting every descendant
+    index: Option<&'a IndexedDocument<'a, D>>,
+    // Addresses of the values yielded so far, in unique mode
+    seen: Option<HashSet<usize>>,
+}
+
+/// Iterator returned by `Selector::find_strict`.
+///
+/// Yields an error for every place where the document does not have the shape the
+/// selector expects, and keeps going with the rest of the document.
+pub struct StrictIter<'a, 'b, D: 'a + Document = Value> {
+    iter: Iter<'a, 'b, D>,
+}
+
+/// Iterator returned by `Selector::find_limited`.
+///
+/// Yields the matches until a limit is hit, then `ErrorKind::LimitExceeded` as its last
+/// item.
+pub struct LimitedIter<'a, 'b, D: 'a + Document = Value> {
+    iter: Iter<'a, 'b, D>,
+    max_results: Option<usize>,
+    results: usize,
+    // Limits that are exceeded before evaluating anything
+    error: Option<Error>,
+    done: bool,
+}
+
+// Candidates produced by the segment at `si` from a single value
+struct Frame<'a, D: 'a + Document> {
+    si: usize,
+    children: Children<'a, D>,
+}
+
+pub enum Children<'a, D: 'a + Document> {
+    Empty,
+    One(Option<(&'a D, Step<'a>)>),
+    // Indexes of the elements still to visit
+    Range(&'a D, ::std::ops::Range<usize>),
+    Array(Enumerate<D::Elements<'a>>),
+    Object(D::Members<'a>),
+    // Looked up in an index
+    Listed(::std::vec::IntoIter<&'a D>),
 }
 
-impl<'a, 'b> Iterator for Iter<'a, 'b> {
-    type Item = &'a Value;
+impl<'a, D: Document> Children<'a, D> {
+    pub fn new(value: &'a D, segment: &Segment) -> Self {
+        match *segment {
+            Segment::Root | Segment::Current | Segment::Descendant => {
+                Children::One(Some((value, Step::Root)))
+            }
+            Segment::Member(ref name) => match value.member(name) {
+                Some((key, child)) => Children::One(Some((child, Step::Key(key)))),
+                None => Children::Empty,
+            },
+            Segment::Index(index) => match value.element(index) {
+                Some(child) => Children::One(Some((child, Step::Index(index)))),
+                None => match value.member_by_key(&Key::Integer(index as i128)) {
+                    Some((key, child)) => Children::One(Some((child, key.into()))),
+                    None => Children::Empty,
+                },
+            },
+            Segment::Integer(key) => match value.member_by_key(&Key::Integer(key)) {
+                Some((key, child)) => Children::One(Some((child, key.into()))),
+                None => Children::Empty,
+            },
+            Segment::Bytes(ref bytes) => match value.member_by_key(&Key::Bytes(bytes)) {
+                Some((key, child)) => Children::One(Some((child, key.into()))),
+                None => Children::Empty,
+            },
+            Segment::Slice(start, end) => match value.array_len() {
+                Some(len) => {
+                    let end = end.min(len);
+                    Children::Range(value, start.min(end)..end)
+                }
+                None => Children::Empty,
+            },
+            Segment::Wildcard | Segment::Filter(_) => Children::all(value),
+        }
+    }
+
+    pub fn all(value: &'a D) -> Self {
+        if let Some(members) = value.members() {
+            return Children::Object(members);
+        }
+        match value.elements() {
+            Some(elements) => Children::Array(elements.enumerate()),
+            None => Children::Empty,
+        }
+    }
+}
+
+impl<'a, D: Document> Iterator for Children<'a, D> {
+    type Item = (&'a D, Step<'a>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(mut current) = self.current.take() {
-            if let Some(criterion) = self.criteria.get(self.ci) {
-                if matches(&mut current, criterion, &self.root) {
-                    // if there are no further criteria
-                    if self.criteria.len() == self.ci + 1 {
-                        let val = current.item.value;
-                        // Hack to prevent overflow
-                        if self.ci > 0 {
-                            self.ci -= 1;
-                        }
-                        self.current = self.stack.pop();
-                        return Some(val);
-                    } else {
-                        self.current = current.next();
-                        self.ci += 1;
-                        self.stack.push(current);
-
-                        if self.current.is_none() {
-                            self.ci -= 1;
-                            self.stack.pop();
-
-                            // Hack to prevent overflow
-                            if self.ci > 0 {
-                                self.ci -= 1;
-                            }
-                            self.current = self.stack.pop();
-                        }
-                    }
-                } else if !self.stack.is_empty() {
-                    // the step and criterion do not match
-                    match self.stack.last_mut().unwrap().next() {
-                        Some(new_cur) => self.current = Some(new_cur),
-                        None => {
-                            self.ci -= 1;
-                            self.current = self.stack.pop();
-                        }
+        match *self {
+            Children::Empty => None,
+            Children::One(ref mut child) => child.take(),
+            Children::Range(value, ref mut range) => {
+                let index = range.next()?;
+                value
+                    .element(index)
+                    .map(|child| (child, Step::Index(index)))
+            }
+            Children::Array(ref mut iter) => iter
+                .next()
+                .map(|(index, child)| (child, Step::Index(index))),
+            Children::Object(ref mut iter) => iter.next().map(|(key, child)| (child, key.into())),
+            Children::Listed(ref mut iter) => iter.next().map(|child| (child, Step::Root)),
+        }
+    }
+}
+
+impl<'a, 'b, D: Document> Iterator for Iter<'a, 'b, D> {
+    type Item = &'a D;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(result) = self.next_result() {
+            if let Ok(value) = result {
+                if let Some(ref mut seen) = self.seen {
+                    if !seen.insert(value as *const D as usize) {
+                        continue;
                     }
                 }
-            } else {
-                // This must be unreachable, because we look forward for empty criteria in
-                //    if self.criteria.len() == self.ci + 1 {
-                unreachable!();
+                return Some(value);
             }
         }
         None
     }
 }
 
-impl<'a, 'b> Iter<'a, 'b> {
-    pub fn new(root: &'a Value, criteria: &'b [Criterion]) -> Self {
-        let root_item = Item::new(root);
-        let step = Step::Root;
-        let root = StackItem::new(Item::new(root), Step::Root);
-        let current = Some(StackItem::new(root_item, step));
+impl<'a, 'b, D: Document> Iterator for StrictIter<'a, 'b, D> {
+    type Item = Result<&'a D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next_result()
+    }
+}
+
+impl<'a, 'b, D: Document> Iterator for LimitedIter<'a, 'b, D> {
+    type Item = Result<&'a D>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = match self.error.take() {
+            Some(e) => Err(e),
+            None => match self.iter.next_result()? {
+                Ok(_) if Some(self.results) == self.max_results => Err(ErrorKind::LimitExceeded(
+                    format!("more than {} results", self.results),
+                )
+                .into()),
+                result => result,
+            },
+        };
+        match result {
+            Ok(_) => self.results += 1,
+            Err(_) => self.done = true,
+        }
+        Some(result)
+    }
+}
+
+impl<'a, 'b, D: Document> Iter<'a, 'b, D> {
+    pub fn new(root: &'a D, segments: &'b [Segment]) -> Self {
+        Self::with_cache(root, segments, SharedCache::default())
+    }
+
+    pub(crate) fn with_cache(
+        root: &'a D,
+        segments: &'b [Segment],
+        cache: SharedCache<'a, D>,
+    ) -> Self {
+        let stack = match segments.first() {
+            Some(segment) => vec![Frame {
+                si: 0,
+                children: Children::new(root, segment),
+            }],
+            None => vec![],
+        };
 
         Self {
-            criteria,
-            current,
+            segments,
             root,
-            stack: vec![],
-            ci: 0,
+            stack,
+            steps: vec![],
+            strict_base: None,
+            cache,
+            index: None,
+            seen: None,
+        }
+    }
+
+    /// Yields every value at most once, at the first path that reaches it. Values are
+    /// told apart by identity: equal values at different places are all yielded.
+    ///
+    /// ```
+    /// # extern crate jsonpath;
+    /// # #[macro_use] extern crate serde_json;
+    /// # fn main() {
+    /// use jsonpath::Selector;
+    ///
+    /// let value = json!({"a": {"b": {"c": 1}}});
+    /// let selector = Selector::new("$..*..*").unwrap();
+    /// assert_eq!(selector.find(&value).count(), 3);
+    /// assert_eq!(selector.find(&value).unique().count(), 2);
+    /// # }
+    /// ```
+    pub fn unique(mut self) -> Self {
+        self.seen = Some(HashSet::new());
+        self
+    }
+
+    pub(crate) fn indexed(mut self, index: Option<&'a IndexedDocument<'a, D>>) -> Self {
+        self.index = index;
+        self
+    }
+
+    // Applies the segments from `si` on to `value`, matched by the segment before
+    #[cfg(feature = "rayon")]
+    pub(crate) fn resume(
+        root: &'a D,
+        segments: &'b [Segment],
+        si: usize,
+        value: &'a D,
+        cache: SharedCache<'a, D>,
+    ) -> Self {
+        Self {
+            segments,
+            root,
+            stack: vec![Frame {
+                si,
+                children: Children::new(value, &segments[si]),
+            }],
+            steps: vec![],
+            strict_base: None,
+            cache,
+            index: None,
+            seen: None,
+        }
+    }
+
+    // Errors are only produced in strict mode
+    fn next_result(&mut self) -> Option<Result<&'a D>> {
+        loop {
+            let (si, value, step) = {
+                let frame = self.stack.last_mut()?;
+                match frame.children.next() {
+                    Some((value, step)) => (frame.si, value, step),
+                    None => {
+                        self.stack.pop();
+                        self.steps.pop();
+                        continue;
+                    }
+                }
+            };
+
+            if let Err(e) = self.spend(Some(self.stack.len())) {
+                return Some(Err(e));
+            }
+
+            if let Segment::Filter(ref expr) = self.segments[si] {
+                let strict_path = self.strict_base.as_ref().map(|_| self.path_to(&step));
+                let matched =
+                    filter::matches(expr, value, self.root, strict_path.as_deref(), &self.cache);
+                // The iterators evaluating the filter stop short when a limit is hit
+                match matched.and_then(|matched| self.spend(None).map(|_| matched)) {
+                    Ok(true) => {}
+                    Ok(false) => continue,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+
+            // The children of a value matched by `..` are matched by it as well, after the
+            // value itself. Nothing is required of their shape in strict mode.
+            if let Segment::Descendant = self.segments[si] {
+                if let Some(found) = self.descendants_named(si, value) {
+                    self.stack.push(Frame {
+                        si: si + 1,
+                        children: Children::Listed(found.into_iter()),
+                    });
+                    self.steps.push(step);
+                    continue;
+                }
+                self.stack.push(Frame {
+                    si,
+                    children: Children::all(value),
+                });
+                self.steps.push(step);
+                match self.segments.get(si + 1) {
+                    Some(next_segment) => {
+                        self.stack.push(Frame {
+                            si: si + 1,
+                            children: Children::new(value, next_segment),
+                        });
+                        self.steps.push(Step::Root);
+                        continue;
+                    }
+                    None => return Some(Ok(value)),
+                }
+            }
+
+            let next_segment = match self.segments.get(si + 1) {
+                Some(next_segment) => next_segment,
+                None => return Some(Ok(value)),
+            };
+
+            let error = match self.strict_base {
+                Some(_) => check_shape(value, next_segment, &self.path_to(&step)).err(),
+                None => None,
+            };
+            self.stack.push(Frame {
+                si: si + 1,
+                children: Children::new(value, next_segment),
+            });
+            self.steps.push(step);
+
+            if let Some(e) = error {
+                return Some(Err(e));
+            }
+        }
+    }
+
+    // Counts a value visited at `depth` against the limits of the query, if it has any,
+    // or only checks that none was hit. The iteration ends once one is.
+    fn spend(&mut self, depth: Option<usize>) -> Result<()> {
+        let spent = match self.cache.borrow_mut().budget {
+            Some(ref mut budget) => match depth {
+                Some(depth) => budget.visit(depth),
+                None => budget.check(),
+            },
+            None => Ok(()),
+        };
+        if spent.is_err() {
+            self.stack.clear();
+        }
+        spent
+    }
+
+    // What `..name` at `si` matches from `value`, when there is an index to look it up in.
+    // Paths are not known that way, so strict mode walks the descendants.
+    fn descendants_named(&self, si: usize, value: &'a D) -> Option<Vec<&'a D>> {
+        match (
+            self.index,
+            self.strict_base.as_ref(),
+            self.segments.get(si + 1),
+        ) {
+            (Some(index), None, Some(Segment::Member(name))) => {
+                index.descendants_named(value, name)
+            }
+            _ => None,
+        }
+    }
+
+    // Normalized path of a child of the value whose children are on top of the stack
+    fn path_to(&self, step: &Step) -> String {
+        let mut path = self.strict_base.clone().unwrap_or_else(|| "$".to_owned());
+        for step in self.steps.iter().chain(Some(step)) {
+            if *step != Step::Root {
+                path.push_str(&step.to_string());
+            }
+        }
+        path
+    }
+}
+
+impl<'a, 'b, D: Document> StrictIter<'a, 'b, D> {
+    // `base` is the normalized path of `root` within the whole document
+    pub fn new(root: &'a D, segments: &'b [Segment], base: &str) -> Self {
+        Self::with_cache(root, segments, base, SharedCache::default())
+    }
+
+    pub(crate) fn with_cache(
+        root: &'a D,
+        segments: &'b [Segment],
+        base: &str,
+        cache: SharedCache<'a, D>,
+    ) -> Self {
+        let mut iter = Iter::with_cache(root, segments, cache);
+        iter.strict_base = Some(base.to_owned());
+        Self { iter }
+    }
+}
+
+impl<'a, 'b, D: Document> LimitedIter<'a, 'b, D> {
+    // `filter_depth` is the nesting of the filters of the selector
+    pub(crate) fn new(iter: Iter<'a, 'b, D>, limits: &EvalLimits, filter_depth: usize) -> Self {
+        iter.cache.borrow_mut().budget = Some(Budget::new(limits));
+        let error = match limits.max_filter_depth {
+            Some(max) if filter_depth > max => Some(
+                ErrorKind::LimitExceeded(format!("filters nested more than {} deep", max)).into(),
+            ),
+            _ => None,
+        };
+        Self {
+            iter,
+            max_results: limits.max_results,
+            results: 0,
+            error,
+            done: false,
         }
     }
 }
@@ -81,7 +450,14 @@ impl<'a, 'b> Iter<'a, 'b> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use plan::compile;
     use serde_json;
+    use structs::Criterion;
+
+    fn find<'a>(root: &'a Value, criteria: &[Criterion]) -> Vec<&'a Value> {
+        let plan = compile(criteria).unwrap();
+        Iter::new(root, &plan.segments).collect()
+    }
 
     #[test]
     fn test_simple_json() {
@@ -93,14 +469,14 @@ mod tests {
             }
         "#;
 
-        let root: Value = serde_json::from_str(&json).unwrap();
+        let root: Value = serde_json::from_str(json).unwrap();
         let criteria = vec![
             Criterion::Root,
             Criterion::NamedChild("dog".to_owned()),
             Criterion::NamedChild("name".to_owned()),
         ];
 
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
+        let found = find(&root, &criteria);
         assert_eq!(found, vec!["Rex"]);
     }
 
@@ -125,7 +501,7 @@ mod tests {
             }
         "#;
 
-        let root: Value = serde_json::from_str(&json).unwrap();
+        let root: Value = serde_json::from_str(json).unwrap();
 
         // $.user.age
         let criteria = vec![
@@ -133,7 +509,7 @@ mod tests {
             Criterion::NamedChild("user".to_owned()),
             Criterion::NamedChild("age".to_owned()),
         ];
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
+        let found = find(&root, &criteria);
         assert_eq!(found, vec![27]);
 
         // $.pets.*.type
@@ -143,7 +519,7 @@ mod tests {
             Criterion::AnyChild,
             Criterion::NamedChild("type".to_owned()),
         ];
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
+        let found = find(&root, &criteria);
         assert_eq!(found, vec!["cat", "dog"]);
 
         // $.pets.*.name
@@ -153,7 +529,7 @@ mod tests {
             Criterion::AnyChild,
             Criterion::NamedChild("name".to_owned()),
         ];
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
+        let found = find(&root, &criteria);
         assert_eq!(found, vec!["Tom", "Rex"]);
 
         // $.user.*
@@ -162,8 +538,12 @@ mod tests {
             Criterion::NamedChild("user".to_owned()),
             Criterion::AnyChild,
         ];
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
-        assert_eq!(found, vec![&Value::from(27), &Value::from("Sergey")]);
+        let found = find(&root, &criteria);
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(found, vec![&Value::from("Sergey"), &Value::from(27)]);
+        } else {
+            assert_eq!(found, vec![&Value::from(27), &Value::from("Sergey")]);
+        }
     }
 
     #[test]
@@ -181,15 +561,19 @@ mod tests {
             }
         "#;
 
-        let root: Value = serde_json::from_str(&json).unwrap();
+        let root: Value = serde_json::from_str(json).unwrap();
         let criteria = vec![
             Criterion::Root,
             Criterion::NamedChild("pet".to_owned()),
             Criterion::AnyChild,
         ];
 
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
-        assert_eq!(found, vec!["Rex", "dog"]);
+        let found = find(&root, &criteria);
+        if cfg!(feature = "preserve_order") {
+            assert_eq!(found, vec!["dog", "Rex"]);
+        } else {
+            assert_eq!(found, vec!["Rex", "dog"]);
+        }
     }
 
     #[test]
@@ -198,10 +582,91 @@ mod tests {
             ["Foo", "Bar", "Baz"]
         "#;
 
-        let root: Value = serde_json::from_str(&json).unwrap();
+        let root: Value = serde_json::from_str(json).unwrap();
         let criteria = vec![Criterion::Root, Criterion::IndexedChild(1)];
 
-        let found: Vec<&Value> = Iter::new(&root, &criteria).collect();
+        let found = find(&root, &criteria);
         assert_eq!(found, vec!["Bar"]);
     }
+
+    #[test]
+    fn test_descendant() {
+        let json = r#"
+            {
+                "name": "root",
+                "pets": [
+                    {"name": "Tom", "toys": [{"name": "ball"}]},
+                    {"name": "Rex"}
+                ]
+            }
+        "#;
+
+        let root: Value = serde_json::from_str(json).unwrap();
+
+        // $..name
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::Descendant,
+            Criterion::NamedChild("name".to_owned()),
+        ];
+        let found = find(&root, &criteria);
+        assert_eq!(found, vec!["root", "Tom", "ball", "Rex"]);
+
+        // $.pets..[0].name
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::NamedChild("pets".to_owned()),
+            Criterion::Descendant,
+            Criterion::IndexedChild(0),
+            Criterion::NamedChild("name".to_owned()),
+        ];
+        let found = find(&root, &criteria);
+        assert_eq!(found, vec!["Tom", "ball"]);
+    }
+
+    #[test]
+    fn test_direct_lookup_misses() {
+        let root: Value =
+            serde_json::from_str(r#"{"list": ["Foo", "Bar"], "map": {"a": 1}}"#).unwrap();
+
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::NamedChild("list".to_owned()),
+            Criterion::NamedChild("a".to_owned()),
+        ];
+        assert_eq!(find(&root, &criteria).len(), 0);
+
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::NamedChild("map".to_owned()),
+            Criterion::IndexedChild(0),
+        ];
+        assert_eq!(find(&root, &criteria).len(), 0);
+
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::NamedChild("list".to_owned()),
+            Criterion::IndexedChild(2),
+        ];
+        assert_eq!(find(&root, &criteria).len(), 0);
+    }
+
+    #[test]
+    fn test_slices_are_clipped() {
+        let root: Value = serde_json::from_str(r#"["Foo", "Bar", "Baz"]"#).unwrap();
+
+        let criteria = vec![Criterion::Root, Criterion::Slice(1..10)];
+        let found = find(&root, &criteria);
+        assert_eq!(found, vec!["Bar", "Baz"]);
+
+        let criteria = vec![Criterion::Root, Criterion::Slice(5..10)];
+        assert_eq!(find(&root, &criteria).len(), 0);
+
+        let criteria = vec![Criterion::Root, Criterion::SliceTo(..usize::MAX)];
+        assert_eq!(find(&root, &criteria).len(), 3);
+
+        let criteria = vec![Criterion::Root, Criterion::SliceFrom(2)];
+        let found = find(&root, &criteria);
+        assert_eq!(found, vec!["Baz"]);
+    }
 }
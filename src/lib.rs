@@ -60,14 +60,56 @@ extern crate pest;
 #[macro_use]
 extern crate pest_derive;
 
-#[cfg(test)]
+#[cfg(feature = "ciborium")]
+extern crate ciborium;
 extern crate lazy_static;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+extern crate regex;
+#[cfg(feature = "rmpv")]
+extern crate rmpv;
+#[cfg(feature = "serde_yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "toml")]
+extern crate toml;
 
+pub mod ast;
+mod complexity;
+mod document;
 mod errors;
+pub mod extract;
 mod filter;
+mod index;
 mod iter;
+mod limits;
+#[cfg(feature = "rayon")]
+mod parallel;
 mod parser;
+mod plan;
+mod raw;
+mod records;
 mod selector;
+mod set;
+mod stream;
 mod structs;
+mod trace;
+pub mod visit;
 
+pub use complexity::{Complexity, ComplexityLimits};
+pub use document::{Document, Key, Kind};
+pub use errors::{Error, ErrorKind, ParseError, Result};
+pub use extract::JsonPathExtract;
+pub use index::{IndexedDocument, Searchable};
+pub use iter::{Iter, LimitedIter, StrictIter};
+pub use limits::EvalLimits;
+pub use records::Records;
 pub use selector::Selector;
+pub use set::{SelectorSet, SetMatches};
+pub use stream::StreamMatch;
+pub use trace::{ComparisonTrace, Outcome, Trace, TraceStep};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use lazy_static::lazy_static;
+    pub use serde_json::Value;
+}
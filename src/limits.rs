@@ -0,0 +1,91 @@
+use std::time::Instant;
+
+use errors::*;
+
+/// Bounds on the work of a single evaluation, see `Selector::find_limited`.
+///
+/// Every bound is optional, `EvalLimits::default()` has none.
+///
+/// ```
+/// # use jsonpath::EvalLimits;
+/// use std::time::{Duration, Instant};
+///
+/// let limits = EvalLimits {
+///     max_nodes: Some(100_000),
+///     max_results: Some(1_000),
+///     deadline: Some(Instant::now() + Duration::from_millis(50)),
+///     ..EvalLimits::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct EvalLimits {
+    /// Values visited, including while evaluating filters.
+    pub max_nodes: Option<usize>,
+
+    /// Matches yielded.
+    pub max_results: Option<usize>,
+
+    /// Filters within filters: 1 for `$.a[?(@.b > 1)]`, 2 for
+    /// `$.a[?(@.b[?(@.c == 1)].d > 1)]`. Checked before anything is evaluated.
+    pub max_filter_depth: Option<usize>,
+
+    /// Nesting of the evaluation: about one level per segment of the selector, and one
+    /// more per level of the document below a `..`.
+    pub max_depth: Option<usize>,
+
+    /// When the evaluation must have finished.
+    pub deadline: Option<Instant>,
+}
+
+// The clock is only read every so many visits
+const DEADLINE_INTERVAL: usize = 1024;
+
+// What a query has used of its limits, shared by the iterators evaluating its filters
+pub struct Budget {
+    limits: EvalLimits,
+    visited: usize,
+    // Once a limit is hit, every iterator of the query stops
+    exceeded: Option<String>,
+}
+
+impl Budget {
+    pub fn new(limits: &EvalLimits) -> Self {
+        Budget {
+            limits: limits.clone(),
+            visited: 0,
+            exceeded: None,
+        }
+    }
+
+    // Counts a value visited `depth` levels below where its iterator started
+    pub fn visit(&mut self, depth: usize) -> Result<()> {
+        self.visited += 1;
+        if self.exceeded.is_none() {
+            self.exceeded = match self.limits {
+                EvalLimits {
+                    max_nodes: Some(max),
+                    ..
+                } if self.visited > max => Some(format!("more than {} nodes visited", max)),
+                EvalLimits {
+                    max_depth: Some(max),
+                    ..
+                } if depth > max => Some(format!("more than {} levels deep", max)),
+                EvalLimits {
+                    deadline: Some(deadline),
+                    ..
+                } if self.visited % DEADLINE_INTERVAL == 1 && Instant::now() >= deadline => {
+                    Some("deadline passed".to_owned())
+                }
+                _ => None,
+            };
+        }
+        self.check()
+    }
+
+    pub fn check(&self) -> Result<()> {
+        match self.exceeded {
+            Some(ref limit) => bail!(ErrorKind::LimitExceeded(limit.clone())),
+            None => Ok(()),
+        }
+    }
+}
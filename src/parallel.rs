@@ -0,0 +1,73 @@
+//! Evaluation of a selector on several threads.
+//!
+//! The segments that may produce many candidates, wildcards, slices and filters, are split
+//! across the threads of rayon's pool, and everything in between is evaluated by `Iter`.
+//! The matches of every candidate are collected in order, so the result is the one `find`
+//! gives.
+use rayon::prelude::*;
+
+use document::Document;
+use filter::{self, SharedCache};
+use iter::{Children, Iter};
+use plan::Segment;
+
+pub fn find<'a, D>(segments: &[Segment], root: &'a D) -> Vec<&'a D>
+where
+    D: Document + Sync,
+{
+    if segments.is_empty() {
+        return vec![];
+    }
+    // The first segment is `$`
+    find_from(segments, 1, root, root, &SharedCache::default())
+}
+
+// Matches of the segments from `si` on, applied to `value`
+fn find_from<'a, D>(
+    segments: &[Segment],
+    si: usize,
+    value: &'a D,
+    root: &'a D,
+    cache: &SharedCache<'a, D>,
+) -> Vec<&'a D>
+where
+    D: Document + Sync,
+{
+    if si == segments.len() {
+        return vec![value];
+    }
+    let split = segments[si..].iter().position(|segment| {
+        matches!(
+            *segment,
+            Segment::Wildcard | Segment::Slice(..) | Segment::Filter(_)
+        )
+    });
+    let split = match split {
+        Some(offset) => si + offset,
+        None => return Iter::resume(root, segments, si, value, cache.clone()).collect(),
+    };
+
+    let parents: Vec<&'a D> = if split == si {
+        vec![value]
+    } else {
+        Iter::resume(root, &segments[..split], si, value, cache.clone()).collect()
+    };
+    let candidates: Vec<&'a D> = parents
+        .iter()
+        .flat_map(|parent| Children::new(*parent, &segments[split]).map(|(child, _)| child))
+        .collect();
+
+    // Caches are not shared between threads, each job of the pool starts its own
+    let found: Vec<Vec<&'a D>> = candidates
+        .par_iter()
+        .map_init(SharedCache::default, |cache, &child| {
+            if let Segment::Filter(ref expr) = segments[split] {
+                if !filter::matches(expr, child, root, None, cache).unwrap_or(false) {
+                    return vec![];
+                }
+            }
+            find_from(segments, split + 1, child, root, cache)
+        })
+        .collect();
+    found.into_iter().flatten().collect()
+}
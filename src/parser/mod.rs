@@ -1,93 +1,333 @@
-use pest::iterators::Pair;
-use pest::Parser;
+use pest::iterators::{Pair, Pairs};
+use pest::{Error as PestError, Parser};
+use regex::Regex;
+use std::str::CharIndices;
 
 use errors::*;
-use std::error::Error as StdError;
-use structs::Criterion;
+use structs::{Criterion, Pattern};
 
 #[derive(Parser)]
 #[grammar = "parser/grammar.pest"]
 struct ExpressionParser;
 
+// Both the parser and the evaluator recurse into nested filters, so the nesting is
+// limited to keep untrusted expressions from overflowing the stack.
+const MAX_NESTING: usize = 32;
+
 pub fn parse(expression: &str) -> Result<Vec<Criterion>> {
+    check_nesting(expression)?;
+
     let mut pairs = ExpressionParser::parse(Rule::expression, expression)
-        .map_err(|e| Error::from_kind(ErrorKind::Parse(e.description().to_owned())))?;
+        .map_err(|e| parse_error(expression, e))?;
 
-    let root = pairs.next().unwrap();
-    let criteria = parse_tokens(root)?;
+    let root = next_pair(&mut pairs, 0, expression)?;
+    let criteria = parse_tokens(root, expression)?;
     Ok(criteria)
 }
 
-fn parse_tokens(element: Pair<Rule>) -> Result<Vec<Criterion>> {
+pub fn check_nesting(expression: &str) -> Result<()> {
+    let mut depth = 0usize;
+    let mut chars = expression.char_indices();
+    // The last character that is not a space, a pattern follows `=~`
+    let mut previous = None;
+    while let Some((offset, c)) = chars.next() {
+        match c {
+            // Brackets in string literals, quoted names and patterns do not nest
+            '\'' => skip_quoted(&mut chars, '\''),
+            '/' if previous == Some('~') => skip_quoted(&mut chars, '/'),
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        if c != ' ' {
+            previous = Some(c);
+        }
+        if depth > MAX_NESTING {
+            let expected = format!("at most {} nested brackets", MAX_NESTING);
+            bail!(ErrorKind::Parse(ParseError::new(
+                expression,
+                offset,
+                vec![expected]
+            )));
+        }
+    }
+    Ok(())
+}
+
+// Skips to the unescaped `end` that closes a quoted string or a pattern
+fn skip_quoted(chars: &mut CharIndices, end: char) {
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            c if c == end => return,
+            _ => {}
+        }
+    }
+}
+
+fn parse_error(expression: &str, error: PestError<Rule>) -> Error {
+    let (offset, expected) = match error {
+        PestError::ParsingError { positives, pos, .. } => {
+            (pos.pos(), positives.iter().map(describe_rule).collect())
+        }
+        PestError::CustomErrorPos { pos, message } => (pos.pos(), vec![message]),
+        PestError::CustomErrorSpan { span, message } => (span.start(), vec![message]),
+    };
+    ErrorKind::Parse(ParseError::new(expression, offset, expected)).into()
+}
+
+// Names a grammar rule the way a user writing the expression would understand it
+fn describe_rule(rule: &Rule) -> String {
+    let description = match *rule {
+        Rule::dollar | Rule::expression => "`$`",
+        Rule::at => "`@`",
+        Rule::ident | Rule::alpha | Rule::special_charaters => "a member name",
+        Rule::number | Rule::float | Rule::digit => "a number",
+        Rule::dot | Rule::double_dot => "`.`",
+        Rule::asterisk => "`*`",
+        Rule::child => "`.<name>`",
+        Rule::any_child => "`.*`",
+        Rule::descendant_child | Rule::descendant_any | Rule::descendant => "`..`",
+        Rule::indexed_child
+        | Rule::integer_child
+        | Rule::bytes_child
+        | Rule::quoted_child
+        | Rule::slice
+        | Rule::slice_to
+        | Rule::slice_from => "`[`",
+        Rule::hex_digits => "hex digits",
+        Rule::equal
+        | Rule::different
+        | Rule::greater
+        | Rule::greater_or_equal
+        | Rule::lower
+        | Rule::lower_or_equal
+        | Rule::matches
+        | Rule::condition
+        | Rule::comparison => "a comparison operator",
+        Rule::regex | Rule::regex_pattern | Rule::regex_flags => "a regular expression",
+        Rule::quoted | Rule::literal => "a string literal",
+        Rule::array => "an array",
+        Rule::sub_expression => "a path",
+        Rule::and => "`&&`",
+        Rule::or => "`||`",
+        Rule::filter => "a filter",
+    };
+    description.to_owned()
+}
+
+// The grammar guarantees the pairs taken with this function. Should the tree still be
+// malformed, it is reported as a parse error rather than a panic.
+fn next_pair<'i>(
+    pairs: &mut Pairs<'i, Rule>,
+    offset: usize,
+    expression: &str,
+) -> Result<Pair<'i, Rule>> {
+    pairs
+        .next()
+        .ok_or_else(|| ErrorKind::Parse(ParseError::new(expression, offset, vec![])).into())
+}
+
+fn parse_index(token: Pair<Rule>, expression: &str) -> Result<usize> {
+    token.as_str().parse().map_err(|_| {
+        let expected = format!("an index no larger than {}", usize::MAX);
+        let offset = token.into_span().start();
+        ErrorKind::Parse(ParseError::new(expression, offset, vec![expected])).into()
+    })
+}
+
+// The digits of `[-digits]`
+fn parse_negative(token: Pair<Rule>, expression: &str) -> Result<i128> {
+    format!("-{}", token.as_str()).parse().map_err(|_| {
+        let expected = format!("an integer key no smaller than {}", i128::MIN);
+        let offset = token.into_span().start();
+        ErrorKind::Parse(ParseError::new(expression, offset, vec![expected])).into()
+    })
+}
+
+fn parse_hex(token: Pair<Rule>, expression: &str) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    // The grammar only lets hex digits through
+    for pair in token.as_str().as_bytes().chunks(2) {
+        let byte = ::std::str::from_utf8(pair)
+            .ok()
+            .filter(|pair| pair.len() == 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok());
+        match byte {
+            Some(byte) => bytes.push(byte),
+            None => {
+                let expected = vec!["pairs of hex digits".to_owned()];
+                let offset = token.into_span().start();
+                bail!(ErrorKind::Parse(ParseError::new(
+                    expression, offset, expected
+                )))
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+// Quoted names and literals escape `'` and `\` with a backslash
+fn unescape(quoted: &str) -> String {
+    let mut unescaped = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// The only flag, `i`, is turned into an inline flag of the pattern
+fn parse_regex(pattern: Pair<Rule>, flags: Pair<Rule>, expression: &str) -> Result<Pattern> {
+    let source = match flags.as_str() {
+        "i" => format!("(?i){}", pattern.as_str()),
+        _ => pattern.as_str().to_owned(),
+    };
+    // The plan keeps the compiled expression
+    match Regex::new(&source) {
+        Ok(regex) => Ok(Pattern {
+            source,
+            regex: Some(regex),
+        }),
+        Err(_) => {
+            let expected = vec!["a valid regular expression".to_owned()];
+            let offset = pattern.into_span().start();
+            bail!(ErrorKind::Parse(ParseError::new(
+                expression, offset, expected
+            )))
+        }
+    }
+}
+
+fn parse_tokens(element: Pair<Rule>, expression: &str) -> Result<Vec<Criterion>> {
     let mut criteria: Vec<Criterion> = vec![];
     for token in element.into_inner() {
+        let offset = token.clone().into_span().start();
         match token.as_rule() {
             Rule::dollar => criteria.push(Criterion::Root),
             Rule::at => criteria.push(Criterion::Element),
-            Rule::condition => match token.into_inner().next().unwrap().as_rule() {
-                Rule::equal => {
-                    criteria.push(Criterion::Equal);
-                }
-                Rule::different => {
-                    criteria.push(Criterion::Different);
-                }
-                Rule::greater_or_equal => {
-                    criteria.push(Criterion::GreaterOrEqual);
-                }
-                Rule::greater => {
-                    criteria.push(Criterion::Greater);
-                }
-                Rule::lower_or_equal => {
-                    criteria.push(Criterion::LowerOrEqual);
-                }
-                Rule::lower => {
-                    criteria.push(Criterion::Lower);
+            Rule::condition => {
+                match next_pair(&mut token.into_inner(), offset, expression)?.as_rule() {
+                    Rule::equal => {
+                        criteria.push(Criterion::Equal);
+                    }
+                    Rule::different => {
+                        criteria.push(Criterion::Different);
+                    }
+                    Rule::greater_or_equal => {
+                        criteria.push(Criterion::GreaterOrEqual);
+                    }
+                    Rule::greater => {
+                        criteria.push(Criterion::Greater);
+                    }
+                    Rule::lower_or_equal => {
+                        criteria.push(Criterion::LowerOrEqual);
+                    }
+                    Rule::lower => {
+                        criteria.push(Criterion::Lower);
+                    }
+                    _ => {}
                 }
-                _ => {}
-            },
+            }
+            Rule::matches => criteria.push(Criterion::Matches),
+            Rule::regex => {
+                let mut iter = token.into_inner();
+                let pattern = next_pair(&mut iter, offset, expression)?;
+                let flags = next_pair(&mut iter, offset, expression)?;
+                criteria.push(Criterion::Regex(parse_regex(pattern, flags, expression)?));
+            }
             Rule::literal => {
-                let literal = token.into_inner().next().unwrap().as_str().to_owned();
-                criteria.push(Criterion::Literal(literal))
+                let literal = next_pair(&mut token.into_inner(), offset, expression)?;
+                criteria.push(Criterion::Literal(unescape(literal.as_str())))
             }
             Rule::number | Rule::float => {
-                let value = token.as_str().parse::<f64>().unwrap();
+                let value = token.as_str().parse::<f64>().map_err(|_| {
+                    let expected = vec!["a number".to_owned()];
+                    Error::from(ErrorKind::Parse(ParseError::new(
+                        expression, offset, expected,
+                    )))
+                })?;
                 criteria.push(Criterion::Float(value))
             }
             Rule::filter => {
-                let filter_criteria = parse_tokens(token)?;
+                let filter_criteria = parse_tokens(token, expression)?;
                 criteria.push(Criterion::Filter(filter_criteria))
             }
             Rule::array => {
-                let array_criteria = parse_tokens(token)?;
+                let array_criteria = parse_tokens(token, expression)?;
                 criteria.push(Criterion::Array(array_criteria))
             }
             Rule::child => {
-                let ident = token.into_inner().next().unwrap().as_str().to_owned();
+                let ident = next_pair(&mut token.into_inner(), offset, expression)?
+                    .as_str()
+                    .to_owned();
                 criteria.push(Criterion::NamedChild(ident))
             }
+            Rule::quoted_child => {
+                let name = next_pair(&mut token.into_inner(), offset, expression)?;
+                criteria.push(Criterion::NamedChild(unescape(name.as_str())))
+            }
             Rule::any_child => criteria.push(Criterion::AnyChild),
+            Rule::descendant_child => {
+                let ident = next_pair(&mut token.into_inner(), offset, expression)?
+                    .as_str()
+                    .to_owned();
+                criteria.push(Criterion::Descendant);
+                criteria.push(Criterion::NamedChild(ident))
+            }
+            Rule::descendant_any => {
+                criteria.push(Criterion::Descendant);
+                criteria.push(Criterion::AnyChild)
+            }
+            Rule::descendant => criteria.push(Criterion::Descendant),
             Rule::indexed_child => {
-                let index: usize = token.into_inner().next().unwrap().as_str().parse()?;
+                let index = parse_index(
+                    next_pair(&mut token.into_inner(), offset, expression)?,
+                    expression,
+                )?;
                 criteria.push(Criterion::IndexedChild(index));
             }
+            Rule::integer_child => {
+                let key = parse_negative(
+                    next_pair(&mut token.into_inner(), offset, expression)?,
+                    expression,
+                )?;
+                // `[-0]` is `[0]`
+                criteria.push(match key {
+                    0 => Criterion::IndexedChild(0),
+                    key => Criterion::IntegerChild(key),
+                });
+            }
+            Rule::bytes_child => {
+                let bytes = parse_hex(
+                    next_pair(&mut token.into_inner(), offset, expression)?,
+                    expression,
+                )?;
+                criteria.push(Criterion::BytesChild(bytes));
+            }
             Rule::slice => {
                 let mut iter = token.into_inner();
-                let from: usize = iter.next().unwrap().as_str().parse()?;
-                let to: usize = iter.next().unwrap().as_str().parse()?;
+                let from = parse_index(next_pair(&mut iter, offset, expression)?, expression)?;
+                let to = parse_index(next_pair(&mut iter, offset, expression)?, expression)?;
                 criteria.push(Criterion::Slice(from..to));
             }
             Rule::slice_to => {
                 let mut iter = token.into_inner();
-                let to: usize = iter.next().unwrap().as_str().parse()?;
+                let to = parse_index(next_pair(&mut iter, offset, expression)?, expression)?;
                 criteria.push(Criterion::SliceTo(..to));
             }
             Rule::slice_from => {
                 let mut iter = token.into_inner();
-                let from: usize = iter.next().unwrap().as_str().parse()?;
+                let from = parse_index(next_pair(&mut iter, offset, expression)?, expression)?;
                 criteria.push(Criterion::SliceFrom(from));
             }
             Rule::sub_expression => {
-                let sub_expression = parse_tokens(token)?;
+                let sub_expression = parse_tokens(token, expression)?;
                 criteria.push(Criterion::SubExpression(sub_expression));
             }
             Rule::and => {
@@ -97,8 +337,10 @@ fn parse_tokens(element: Pair<Rule>) -> Result<Vec<Criterion>> {
                 criteria.push(Criterion::Or);
             }
             rule => {
-                println!("Unable to reach rule: {:?}", rule);
-                unreachable!()
+                let expected = vec![describe_rule(&rule)];
+                bail!(ErrorKind::Parse(ParseError::new(
+                    expression, offset, expected
+                )))
             }
         }
     }
@@ -109,6 +351,7 @@ fn parse_tokens(element: Pair<Rule>) -> Result<Vec<Criterion>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use structs::Criteria;
 
     #[test]
     fn test_root() {
@@ -131,6 +374,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_quoted_child() {
+        let criteria = parse(r"$['a b']..['it\'s'][h'00']").unwrap();
+        assert_eq!(
+            criteria,
+            vec![
+                Criterion::Root,
+                Criterion::NamedChild("a b".to_owned()),
+                Criterion::Descendant,
+                Criterion::NamedChild("it's".to_owned()),
+                Criterion::BytesChild(vec![0]),
+            ]
+        );
+    }
+
     #[test]
     fn test_any_child() {
         let exp = "$.*.title";
@@ -145,6 +403,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_descendant() {
+        let exp = "$..book..*..[0]";
+        let criteria = parse(exp).unwrap();
+        assert_eq!(
+            criteria,
+            vec![
+                Criterion::Root,
+                Criterion::Descendant,
+                Criterion::NamedChild("book".to_owned()),
+                Criterion::Descendant,
+                Criterion::AnyChild,
+                Criterion::Descendant,
+                Criterion::IndexedChild(0),
+            ]
+        );
+    }
+
     #[test]
     fn test_indexed_child() {
         let exp = "$.books[34]";
@@ -159,6 +435,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_integer_child() {
+        let criteria = parse("$[-1][-0]").unwrap();
+        assert_eq!(
+            criteria,
+            vec![
+                Criterion::Root,
+                Criterion::IntegerChild(-1),
+                Criterion::IndexedChild(0),
+            ]
+        );
+        let min = format!("$[{}]", i128::MIN);
+        assert_eq!(parse(&min).unwrap()[1], Criterion::IntegerChild(i128::MIN));
+        assert!(parse("$[-170141183460469231731687303715884105729]").is_err());
+        assert!(parse("$[-]").is_err());
+    }
+
     #[test]
     fn test_slice() {
         let exp = "$.books[4:7]";
@@ -237,4 +530,120 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_filter_regex() {
+        let exp = "$.books[?(@.title =~ /^Moby \\/ Dick$/i)]";
+        let criteria = parse(exp).unwrap();
+
+        let filter = vec![
+            Criterion::Element,
+            Criterion::NamedChild("title".to_owned()),
+            Criterion::Matches,
+            Criterion::Regex(Pattern::new("(?i)^Moby \\/ Dick$".to_owned())),
+        ];
+
+        assert_eq!(
+            criteria,
+            vec![
+                Criterion::Root,
+                Criterion::NamedChild("books".to_owned()),
+                Criterion::Filter(filter),
+            ]
+        );
+    }
+
+    fn parse_error(exp: &str) -> ParseError {
+        match parse(exp).unwrap_err().kind() {
+            ErrorKind::Parse(err) => err.clone(),
+            kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_error_position_and_expected() {
+        let err = parse_error("$.books[?(@.price <> 3)]");
+        assert_eq!(err.offset(), 19);
+        assert_eq!(err.line(), 1);
+        assert_eq!(err.column(), 20);
+        assert_eq!(err.token(), Some(">"));
+        assert_eq!(
+            err.to_string(),
+            "unexpected `>` at line 1, column 20, \
+             expected a number, a string literal, an array or a path"
+        );
+        assert_eq!(
+            err.render(),
+            "$.books[?(@.price <> 3)]\n                   ^"
+        );
+    }
+
+    #[test]
+    fn test_error_missing_root() {
+        let err = parse_error("store.bicycle");
+        assert_eq!(err.offset(), 0);
+        assert_eq!(err.token(), Some("store"));
+        assert_eq!(err.expected(), &["`$`".to_owned()]);
+    }
+
+    #[test]
+    fn test_error_invalid_regex() {
+        let err = parse_error("$.books[?(@.title =~ /(unclosed/)]");
+        assert_eq!(err.offset(), 22);
+        assert_eq!(err.expected(), &["a valid regular expression".to_owned()]);
+    }
+
+    #[test]
+    fn test_error_index_overflow() {
+        let err = parse_error("$[99999999999999999999999]");
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.token(), Some("99999999999999999999999"));
+    }
+
+    #[test]
+    fn test_bytes_child() {
+        let criteria = parse("$[h'CAfe'][h'']").unwrap();
+        assert_eq!(
+            criteria,
+            vec![
+                Criterion::Root,
+                Criterion::BytesChild(vec![0xca, 0xfe]),
+                Criterion::BytesChild(vec![]),
+            ]
+        );
+
+        let err = parse_error("$.key[h'abc']");
+        assert_eq!(err.offset(), 8);
+        assert_eq!(err.expected(), &["pairs of hex digits".to_owned()]);
+    }
+
+    #[test]
+    fn test_canonical_criteria_parse_back() {
+        let canonical = |expression| Criteria(&parse(expression).unwrap()).to_string();
+        for &(expression, expected) in &[
+            ("$", "$"),
+            (
+                "$..book..*..[0][1:3][:2][4:]",
+                "$..book..*..[0][1:3][:2][4:]",
+            ),
+            ("$[h'CAfe'].a", "$[h'cafe'].a"),
+            (
+                "$.books[?(@.price<10.50)&&?($.a..b[?(@.c=='x y')].d!= 3)]",
+                "$.books[?(@.price < 10.5) && ?($.a..b[?(@.c == 'x y')].d != 3)]",
+            ),
+            (
+                "$.a[?(@.t =~ /^a\\/b/i) || ?(@.u=~/x/)]",
+                "$.a[?(@.t =~ /^a\\/b/i) || ?(@.u =~ /x/)]",
+            ),
+            ("$['ab']['a b']..['c.d']", "$.ab['a b']..['c.d']"),
+            ("$[?(@['']=='it\\'s \\\\')]", "$[?(@[''] == 'it\\'s \\\\')]"),
+            (
+                "$.a[?(@.n == [1,2]) || ?(@.f == [1.5,2.0]) || ?(@.s >= ['a',  'b'])]",
+                "$.a[?(@.n == [1, 2]) || ?(@.f == [1.5, 2.0]) || ?(@.s >= ['a', 'b'])]",
+            ),
+        ] {
+            assert_eq!(canonical(expression), expected);
+            assert_eq!(parse(expected).unwrap(), parse(expression).unwrap());
+        }
+    }
 }
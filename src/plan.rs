@@ -0,0 +1,517 @@
+//! Execution plan of a selector.
+//!
+//! The parser produces a flat list of criteria, in which filters are still sequences of
+//! paths, operators, values and `&&`/`||` separators. `compile` turns them into a tree
+//! once, when the selector is created: filters become boolean expressions, operators and
+//! literals are resolved and regular expressions are compiled, so that the evaluation
+//! does not have to re-split or allocate anything per node. Paths in filters that start
+//! from `$` get a slot in the cache of a query, so that they are evaluated only once.
+use regex::Regex;
+use std::fmt;
+
+use errors::*;
+use structs::{Criterion, Hex};
+
+#[derive(Clone)]
+pub struct Plan {
+    pub segments: Vec<Segment>,
+
+    // Absolute paths in filters are evaluated once per query, their results are kept in
+    // this slot of the query cache
+    pub slot: Option<usize>,
+}
+
+#[derive(Clone)]
+pub enum Segment {
+    // $
+    Root,
+
+    // @
+    Current,
+
+    // .name
+    Member(String),
+
+    // [123], also the member with the integer key 123 of an object
+    Index(usize),
+
+    // [-1], the member with a negative integer key
+    Integer(i128),
+
+    // [h'cafe']
+    Bytes(Vec<u8>),
+
+    // Half-open range of indexes, clipped to the length of the array
+    Slice(usize, usize),
+
+    // .*
+    Wildcard,
+
+    // The value and all its descendants, in document order
+    Descendant,
+
+    // [?(expression)]
+    Filter(Expr),
+}
+
+#[derive(Clone)]
+pub enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+
+    // The path matches at least one value
+    Exists(Plan),
+
+    Compare(Plan, Operator, Operand),
+}
+
+/// Comparison operator of a filter condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operator {
+    /// `==`
+    Equal,
+    /// `!=`
+    Different,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterOrEqual,
+    /// `<`
+    Lower,
+    /// `<=`
+    LowerOrEqual,
+    /// `=~`, against a regular expression
+    Matches,
+}
+
+#[derive(Clone)]
+pub enum Operand {
+    String(String),
+    Number(f64),
+
+    // Compares true when any of the operands does
+    Array(Vec<Operand>),
+
+    // An absolute path
+    Path(Plan),
+
+    Regex(Regex),
+}
+
+impl Operator {
+    pub(crate) fn apply<T: PartialOrd + ?Sized>(self, left: &T, right: &T) -> bool {
+        match self {
+            Operator::Equal => left == right,
+            Operator::Different => left != right,
+            Operator::Greater => left > right,
+            Operator::GreaterOrEqual => left >= right,
+            Operator::Lower => left < right,
+            Operator::LowerOrEqual => left <= right,
+            Operator::Matches => false,
+        }
+    }
+
+    pub(crate) fn from_criterion(criterion: &Criterion) -> Option<Self> {
+        let operator = match *criterion {
+            Criterion::Equal => Operator::Equal,
+            Criterion::Different => Operator::Different,
+            Criterion::Greater => Operator::Greater,
+            Criterion::GreaterOrEqual => Operator::GreaterOrEqual,
+            Criterion::Lower => Operator::Lower,
+            Criterion::LowerOrEqual => Operator::LowerOrEqual,
+            Criterion::Matches => Operator::Matches,
+            _ => return None,
+        };
+        Some(operator)
+    }
+
+    pub(crate) fn to_criterion(self) -> Criterion {
+        match self {
+            Operator::Equal => Criterion::Equal,
+            Operator::Different => Criterion::Different,
+            Operator::Greater => Criterion::Greater,
+            Operator::GreaterOrEqual => Criterion::GreaterOrEqual,
+            Operator::Lower => Criterion::Lower,
+            Operator::LowerOrEqual => Criterion::LowerOrEqual,
+            Operator::Matches => Criterion::Matches,
+        }
+    }
+}
+
+pub fn compile(criteria: &[Criterion]) -> Result<Plan> {
+    Compiler::default().plan(criteria)
+}
+
+// Selectors compiled by the same compiler share the slots of the query cache
+#[derive(Default)]
+pub struct Compiler {
+    // Number of absolute sub-paths seen so far
+    slots: usize,
+}
+
+impl Compiler {
+    pub fn plan(&mut self, criteria: &[Criterion]) -> Result<Plan> {
+        let segments = criteria
+            .iter()
+            .map(|criterion| self.segment(criterion))
+            .collect::<Result<Vec<Segment>>>()?;
+        Ok(Plan {
+            segments,
+            slot: None,
+        })
+    }
+
+    // Paths of filters starting with `$` do not depend on the filtered item
+    fn sub_plan(&mut self, criteria: &[Criterion]) -> Result<Plan> {
+        let mut plan = self.plan(criteria)?;
+        if let Some(&Segment::Root) = plan.segments.first() {
+            plan.slot = Some(self.slots);
+            self.slots += 1;
+        }
+        Ok(plan)
+    }
+
+    pub fn segment(&mut self, criterion: &Criterion) -> Result<Segment> {
+        let segment = match *criterion {
+            Criterion::Root => Segment::Root,
+            Criterion::Element => Segment::Current,
+            Criterion::NamedChild(ref name) => Segment::Member(name.clone()),
+            Criterion::IndexedChild(index) => Segment::Index(index),
+            Criterion::IntegerChild(key) => Segment::Integer(key),
+            Criterion::BytesChild(ref bytes) => Segment::Bytes(bytes.clone()),
+            // The end of `[start:end]` is inclusive
+            Criterion::Slice(ref range) => Segment::Slice(range.start, range.end.saturating_add(1)),
+            Criterion::SliceTo(ref range_to) => Segment::Slice(0, range_to.end),
+            Criterion::SliceFrom(from) => Segment::Slice(from, usize::MAX),
+            Criterion::AnyChild => Segment::Wildcard,
+            Criterion::Descendant => Segment::Descendant,
+            Criterion::Filter(ref criteria) => Segment::Filter(self.filter(criteria)?),
+            ref criterion => bail!(ErrorKind::Compile(format!(
+                "{:?} is not a path segment",
+                criterion
+            ))),
+        };
+        Ok(segment)
+    }
+
+    // `||` binds weaker than `&&`
+    fn filter(&mut self, criteria: &[Criterion]) -> Result<Expr> {
+        let mut alternatives = vec![];
+        for alternative in criteria.split(|c| *c == Criterion::Or) {
+            let mut conditions = vec![];
+            for condition in alternative.split(|c| *c == Criterion::And) {
+                conditions.push(self.condition(condition)?);
+            }
+            alternatives.push(match conditions.len() {
+                1 => conditions.remove(0),
+                _ => Expr::And(conditions),
+            });
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.remove(0),
+            _ => Expr::Or(alternatives),
+        })
+    }
+
+    fn condition(&mut self, criteria: &[Criterion]) -> Result<Expr> {
+        match criteria.first() {
+            Some(&Criterion::Root) | Some(&Criterion::Element) => {}
+            _ => bail!(ErrorKind::Compile(
+                "a filter condition must start with `$` or `@`".to_owned()
+            )),
+        }
+
+        let position = criteria
+            .iter()
+            .position(|c| Operator::from_criterion(c).is_some());
+        match position {
+            None => Ok(Expr::Exists(self.sub_plan(criteria)?)),
+            Some(position) => {
+                let path = self.sub_plan(&criteria[..position])?;
+                let operator =
+                    Operator::from_criterion(&criteria[position]).unwrap_or(Operator::Equal);
+                let operand = match &criteria[position + 1..] {
+                    [operand] => self.operand(operand)?,
+                    _ => bail!(ErrorKind::Compile(
+                        "a comparison needs exactly one operand".to_owned()
+                    )),
+                };
+                match (operator, &operand) {
+                    (Operator::Matches, &Operand::Regex(_)) => {}
+                    (Operator::Matches, _) | (_, &Operand::Regex(_)) => {
+                        bail!(ErrorKind::Compile(
+                            "regular expressions can only be used with `=~`".to_owned()
+                        ))
+                    }
+                    _ => {}
+                }
+                Ok(Expr::Compare(path, operator, operand))
+            }
+        }
+    }
+
+    fn operand(&mut self, criterion: &Criterion) -> Result<Operand> {
+        let operand = match *criterion {
+            Criterion::Literal(ref content) => Operand::String(content.clone()),
+            Criterion::Float(value) => Operand::Number(value),
+            Criterion::Array(ref items) => Operand::Array(
+                items
+                    .iter()
+                    .map(|item| self.operand(item))
+                    .collect::<Result<_>>()?,
+            ),
+            Criterion::SubExpression(ref criteria) => Operand::Path(self.sub_plan(criteria)?),
+            Criterion::Regex(ref pattern) => Operand::Regex(pattern.compile()?),
+            ref criterion => bail!(ErrorKind::Compile(format!(
+                "{:?} is not a comparison operand",
+                criterion
+            ))),
+        };
+        Ok(operand)
+    }
+}
+
+// The plan is explained as an indented tree, one node per line
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.explain(f, 0)
+    }
+}
+
+fn line(f: &mut fmt::Formatter, depth: usize, args: fmt::Arguments) -> fmt::Result {
+    writeln!(f, "{:indent$}{}", "", args, indent = depth * 2)
+}
+
+impl Plan {
+    // Deepest nesting of filters, 1 for `$.a[?(@.b)]`
+    pub fn filter_depth(&self) -> usize {
+        self.segments
+            .iter()
+            .map(|segment| match *segment {
+                Segment::Filter(ref expr) => 1 + expr.filter_depth(),
+                _ => 0,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn explain(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        for segment in &self.segments {
+            segment.explain(f, depth)?;
+        }
+        Ok(())
+    }
+}
+
+impl Segment {
+    fn explain(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        line(f, depth, format_args!("{}", self))?;
+        match *self {
+            Segment::Filter(ref expr) => expr.explain(f, depth + 1),
+            _ => Ok(()),
+        }
+    }
+}
+
+// A segment on its own line of the plan, without the expression of filters
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Segment::Root => write!(f, "root"),
+            Segment::Current => write!(f, "current"),
+            Segment::Member(ref name) => write!(f, "member {:?}", name),
+            Segment::Index(index) => write!(f, "index {}", index),
+            Segment::Integer(key) => write!(f, "key {}", key),
+            Segment::Bytes(ref bytes) => write!(f, "key {}", Hex(bytes)),
+            Segment::Slice(start, usize::MAX) => write!(f, "slice {}..", start),
+            Segment::Slice(start, end) => write!(f, "slice {}..{}", start, end),
+            Segment::Wildcard => write!(f, "wildcard"),
+            Segment::Descendant => write!(f, "descendants"),
+            Segment::Filter(_) => write!(f, "filter"),
+        }
+    }
+}
+
+impl Expr {
+    fn filter_depth(&self) -> usize {
+        match *self {
+            Expr::Or(ref exprs) | Expr::And(ref exprs) => {
+                exprs.iter().map(Expr::filter_depth).max().unwrap_or(0)
+            }
+            Expr::Exists(ref path) => path.filter_depth(),
+            Expr::Compare(ref path, _, ref operand) => {
+                path.filter_depth().max(operand.filter_depth())
+            }
+        }
+    }
+
+    fn explain(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        match *self {
+            Expr::Or(ref exprs) | Expr::And(ref exprs) => {
+                let name = match *self {
+                    Expr::Or(_) => "or",
+                    _ => "and",
+                };
+                line(f, depth, format_args!("{}", name))?;
+                for expr in exprs {
+                    expr.explain(f, depth + 1)?;
+                }
+                Ok(())
+            }
+            Expr::Exists(ref path) => {
+                line(f, depth, format_args!("exists"))?;
+                path.explain(f, depth + 1)
+            }
+            Expr::Compare(ref path, operator, ref operand) => {
+                line(f, depth, format_args!("compare {}", operator))?;
+                path.explain(f, depth + 1)?;
+                operand.explain(f, depth + 1)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match *self {
+            Operator::Equal => "==",
+            Operator::Different => "!=",
+            Operator::Greater => ">",
+            Operator::GreaterOrEqual => ">=",
+            Operator::Lower => "<",
+            Operator::LowerOrEqual => "<=",
+            Operator::Matches => "=~",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+impl Operand {
+    fn filter_depth(&self) -> usize {
+        match *self {
+            Operand::Path(ref path) => path.filter_depth(),
+            Operand::Array(ref items) => items.iter().map(Operand::filter_depth).max().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn explain(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        match *self {
+            Operand::String(ref content) => line(f, depth, format_args!("string {:?}", content)),
+            Operand::Number(value) => line(f, depth, format_args!("number {}", value)),
+            Operand::Array(ref items) => {
+                line(f, depth, format_args!("any of"))?;
+                for item in items {
+                    item.explain(f, depth + 1)?;
+                }
+                Ok(())
+            }
+            Operand::Path(ref path) => {
+                line(f, depth, format_args!("path"))?;
+                path.explain(f, depth + 1)
+            }
+            Operand::Regex(ref regex) => line(f, depth, format_args!("regex {:?}", regex.as_str())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::parse;
+    use structs::Pattern;
+
+    fn explain(expression: &str) -> String {
+        compile(&parse(expression).unwrap()).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_explain_path() {
+        assert_eq!(
+            explain("$.books[2].*[1:3][:2][4:]"),
+            "root\n\
+             member \"books\"\n\
+             index 2\n\
+             wildcard\n\
+             slice 1..4\n\
+             slice 0..2\n\
+             slice 4..\n"
+        );
+    }
+
+    #[test]
+    fn test_explain_filter() {
+        assert_eq!(
+            explain(
+                "$.books[?(@.price < 10) || ?(@.category == 'fiction') && \
+                 ?(@.author == $.authors[0].name)]"
+            ),
+            "root\n\
+             member \"books\"\n\
+             filter\n\
+             \x20 or\n\
+             \x20   compare <\n\
+             \x20     current\n\
+             \x20     member \"price\"\n\
+             \x20     number 10\n\
+             \x20   and\n\
+             \x20     compare ==\n\
+             \x20       current\n\
+             \x20       member \"category\"\n\
+             \x20       string \"fiction\"\n\
+             \x20     compare ==\n\
+             \x20       current\n\
+             \x20       member \"author\"\n\
+             \x20       path\n\
+             \x20         root\n\
+             \x20         member \"authors\"\n\
+             \x20         index 0\n\
+             \x20         member \"name\"\n"
+        );
+    }
+
+    #[test]
+    fn test_explain_operands() {
+        assert_eq!(
+            explain("$[?(@.title =~ /^moby/i)][?(@.author == ['Nigel Rees', 'Herman Melville'])]"),
+            "root\n\
+             filter\n\
+             \x20 compare =~\n\
+             \x20   current\n\
+             \x20   member \"title\"\n\
+             \x20   regex \"(?i)^moby\"\n\
+             filter\n\
+             \x20 compare ==\n\
+             \x20   current\n\
+             \x20   member \"author\"\n\
+             \x20   any of\n\
+             \x20     string \"Nigel Rees\"\n\
+             \x20     string \"Herman Melville\"\n"
+        );
+    }
+
+    #[test]
+    fn test_filter_depth() {
+        let depth = |expression| compile(&parse(expression).unwrap()).unwrap().filter_depth();
+        assert_eq!(depth("$.a.b"), 0);
+        assert_eq!(depth("$.a[?(@.b > 1)][?(@.c == 2)]"), 1);
+        assert_eq!(depth("$.a[?(@.b[?(@.c == 1)].d > 1)]"), 2);
+        assert_eq!(
+            depth("$.a[?(@.b > 1) || ?(@.c == $.d[?(@.e =~ /x/)].f)]"),
+            2
+        );
+    }
+
+    #[test]
+    fn test_compile_rejects_regex_without_matches() {
+        let criteria = vec![
+            Criterion::Root,
+            Criterion::Filter(vec![
+                Criterion::Element,
+                Criterion::Equal,
+                Criterion::Regex(Pattern::new("a".to_owned())),
+            ]),
+        ];
+        assert!(compile(&criteria).is_err());
+    }
+}
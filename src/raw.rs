@@ -0,0 +1,357 @@
+//! Evaluation of a selector on raw JSON text.
+//!
+//! As in streaming evaluation, the segments of the plan are run as a set of states while
+//! the text is parsed, and members and elements no state can match are skipped without
+//! being allocated. Matches are borrowed from the text as `RawValue`s, and the matches
+//! inside a match are found by scanning its text again. A candidate of a filter is parsed
+//! as a `Value` to evaluate the filter, then its text is scanned for the rest of the
+//! selector. Since every scan starts a new deserializer, the nesting of the document is
+//! limited here as serde_json limits it in a single pass.
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use serde_json::{self, Value};
+use std::borrow::Cow;
+use std::fmt;
+
+use errors::*;
+use filter::{self, SharedCache};
+use plan::{Expr, Operand, Plan, Segment};
+use stream::{advance, closure, is_match};
+use structs::Step;
+
+// serde_json rejects documents with this many nested arrays and objects
+const MAX_DEPTH: usize = 128;
+
+pub fn find_in_str<'de, T>(segments: &[Segment], json: &'de str) -> Result<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    check(segments)?;
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let found = run(segments, &mut deserializer)?;
+    deserializer.end()?;
+    convert(found)
+}
+
+pub fn find_in_slice<'de, T>(segments: &[Segment], bytes: &'de [u8]) -> Result<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    check(segments)?;
+    let mut deserializer = serde_json::Deserializer::from_slice(bytes);
+    let found = run(segments, &mut deserializer)?;
+    deserializer.end()?;
+    convert(found)
+}
+
+// Filters are evaluated on a candidate alone, without the rest of the document
+fn check(segments: &[Segment]) -> Result<()> {
+    for segment in segments {
+        if let Segment::Filter(ref expr) = *segment {
+            check_expr(expr)?;
+        }
+    }
+    Ok(())
+}
+
+fn check_expr(expr: &Expr) -> Result<()> {
+    match *expr {
+        Expr::Or(ref exprs) | Expr::And(ref exprs) => exprs.iter().try_for_each(check_expr),
+        Expr::Exists(ref path) => check_path(path),
+        Expr::Compare(ref path, _, ref operand) => {
+            check_path(path)?;
+            check_operand(operand)
+        }
+    }
+}
+
+fn check_operand(operand: &Operand) -> Result<()> {
+    match *operand {
+        Operand::Path(ref path) => check_path(path),
+        Operand::Array(ref items) => items.iter().try_for_each(check_operand),
+        _ => Ok(()),
+    }
+}
+
+fn check_path(path: &Plan) -> Result<()> {
+    if path.slot.is_some() {
+        bail!(ErrorKind::Unsupported(
+            "absolute paths in filters of raw evaluation".to_owned()
+        ));
+    }
+    check(&path.segments)
+}
+
+fn convert<'de, T>(found: Vec<&'de RawValue>) -> Result<Vec<T>>
+where
+    T: Deserialize<'de>,
+{
+    found
+        .into_iter()
+        .map(|raw| serde_json::from_str(raw.get()).map_err(Error::from))
+        .collect()
+}
+
+fn run<'de, D>(
+    segments: &[Segment],
+    deserializer: D,
+) -> ::std::result::Result<Vec<&'de RawValue>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut scanner = Scanner {
+        segments,
+        found: vec![],
+    };
+    // The root is matched by the first segment, `$`
+    let states = closure(segments, vec![1]);
+    scanner.node(deserializer, states, 0)?;
+    Ok(scanner.found)
+}
+
+struct Scanner<'s, 'de> {
+    segments: &'s [Segment],
+    found: Vec<&'de RawValue>,
+}
+
+impl<'s, 'de> Scanner<'s, 'de> {
+    // `depth` is the number of arrays and objects around the value
+    fn node<D>(
+        &mut self,
+        deserializer: D,
+        states: Vec<usize>,
+        depth: usize,
+    ) -> ::std::result::Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !is_match(self.segments, &states) {
+            return deserializer.deserialize_any(NodeVisitor {
+                scanner: self,
+                states,
+                depth,
+            });
+        }
+        let raw = <&'de RawValue>::deserialize(deserializer)?;
+        self.matched(raw, states, depth).map_err(de::Error::custom)
+    }
+
+    // A value whose text has been read whole, either because it matched or because a
+    // filter had to be evaluated on it
+    fn matched(
+        &mut self,
+        raw: &'de RawValue,
+        states: Vec<usize>,
+        depth: usize,
+    ) -> serde_json::Result<()> {
+        if is_match(self.segments, &states) {
+            self.found.push(raw);
+        }
+        if states.iter().all(|&state| state == self.segments.len()) {
+            return Ok(());
+        }
+        let mut deserializer = serde_json::Deserializer::from_str(raw.get());
+        deserializer.deserialize_any(NodeVisitor {
+            scanner: self,
+            states,
+            depth,
+        })
+    }
+
+    // States of the child at `step`, along with the states of the filters it has to pass
+    fn step(&self, states: &[usize], step: &Step) -> (Vec<usize>, Vec<usize>) {
+        let filters = states
+            .iter()
+            .cloned()
+            .filter(|&state| matches!(self.segments.get(state), Some(&Segment::Filter(_))))
+            .collect();
+        (advance(self.segments, states, step), filters)
+    }
+
+    fn filtered(
+        &mut self,
+        raw: &'de RawValue,
+        mut states: Vec<usize>,
+        filters: &[usize],
+        depth: usize,
+    ) -> serde_json::Result<()> {
+        let value: Value = serde_json::from_str(raw.get())?;
+        for &state in filters {
+            if let Some(Segment::Filter(expr)) = self.segments.get(state) {
+                let cache = SharedCache::default();
+                if filter::matches(expr, &value, &value, None, &cache).unwrap_or(false)
+                    && !states.contains(&(state + 1))
+                {
+                    states.push(state + 1);
+                }
+            }
+        }
+        let states = closure(self.segments, states);
+        if states.is_empty() {
+            return Ok(());
+        }
+        self.matched(raw, states, depth)
+    }
+}
+
+struct NodeSeed<'w, 's: 'w, 'de: 'w> {
+    scanner: &'w mut Scanner<'s, 'de>,
+    states: Vec<usize>,
+    depth: usize,
+}
+
+impl<'w, 's, 'de> DeserializeSeed<'de> for NodeSeed<'w, 's, 'de> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> ::std::result::Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.scanner.node(deserializer, self.states, self.depth)
+    }
+}
+
+// Member names, borrowed from the text unless they contain escapes
+struct NameSeed;
+
+impl<'de> DeserializeSeed<'de> for NameSeed {
+    type Value = Cow<'de, str>;
+
+    fn deserialize<D>(self, deserializer: D) -> ::std::result::Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for NameSeed {
+    type Value = Cow<'de, str>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a member name")
+    }
+
+    fn visit_borrowed_str<E: de::Error>(
+        self,
+        name: &'de str,
+    ) -> ::std::result::Result<Self::Value, E> {
+        Ok(Cow::Borrowed(name))
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> ::std::result::Result<Self::Value, E> {
+        Ok(Cow::Owned(name.to_owned()))
+    }
+}
+
+struct NodeVisitor<'w, 's: 'w, 'de: 'w> {
+    scanner: &'w mut Scanner<'s, 'de>,
+    states: Vec<usize>,
+    depth: usize,
+}
+
+impl<'w, 's, 'de> NodeVisitor<'w, 's, 'de> {
+    // Depth of the children of an array or object, failing as serde_json would
+    fn enter<E: de::Error>(&self) -> ::std::result::Result<usize, E> {
+        let depth = self.depth + 1;
+        if depth >= MAX_DEPTH {
+            return Err(de::Error::custom("recursion limit exceeded"));
+        }
+        Ok(depth)
+    }
+}
+
+impl<'w, 's, 'de> Visitor<'de> for NodeVisitor<'w, 's, 'de> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let depth = self.enter()?;
+        let scanner = self.scanner;
+        let mut index = 0;
+        loop {
+            let (states, filters) = scanner.step(&self.states, &Step::Index(index));
+            let more = if !filters.is_empty() {
+                match seq.next_element::<&'de RawValue>()? {
+                    Some(raw) => {
+                        scanner
+                            .filtered(raw, states, &filters, depth)
+                            .map_err(de::Error::custom)?;
+                        true
+                    }
+                    None => false,
+                }
+            } else if states.is_empty() {
+                seq.next_element::<IgnoredAny>()?.is_some()
+            } else {
+                seq.next_element_seed(NodeSeed {
+                    scanner: &mut *scanner,
+                    states,
+                    depth,
+                })?
+                .is_some()
+            };
+            if !more {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let depth = self.enter()?;
+        let scanner = self.scanner;
+        while let Some(name) = map.next_key_seed(NameSeed)? {
+            let (states, filters) = scanner.step(&self.states, &Step::Key(&name));
+            if !filters.is_empty() {
+                let raw = map.next_value::<&'de RawValue>()?;
+                scanner
+                    .filtered(raw, states, &filters, depth)
+                    .map_err(de::Error::custom)?;
+            } else if states.is_empty() {
+                map.next_value::<IgnoredAny>()?;
+            } else {
+                map.next_value_seed(NodeSeed {
+                    scanner: &mut *scanner,
+                    states,
+                    depth,
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use serde_json::{self, Value};
+use std::io::BufRead;
+
+use errors::*;
+use selector::Selector;
+
+// Input is read by whole lines, at least this many bytes at a time
+const MIN_READ: usize = 4096;
+
+/// Iterator returned by `Selector::find_records`.
+///
+/// Yields `(record_number, matches)` for every JSON value of the input, numbered from 1.
+/// Values may be separated by newlines (JSON Lines, NDJSON), by other whitespace or by
+/// nothing at all, and may span several lines. A malformed record is yielded as
+/// `ErrorKind::Json`, and the input is resumed after it, but never past the end of the
+/// line it starts on: after the bracket closing a malformed object or array, or a
+/// mismatched one, after the quote closing a string, and for anything else at the next
+/// `{` or `[`. An I/O error is yielded as `ErrorKind::Io` after the records read before
+/// it, and ends the iteration.
+pub struct Records<'s, R> {
+    selector: &'s Selector,
+    reader: R,
+    buffer: Vec<u8>,
+    // Start of the unparsed part of `buffer`
+    position: usize,
+    record: usize,
+    eof: bool,
+    // Reported once the records read before it are
+    error: Option<Error>,
+}
+
+impl<'s, R: BufRead> Records<'s, R> {
+    pub fn new(selector: &'s Selector, reader: R) -> Self {
+        Self {
+            selector,
+            reader,
+            buffer: vec![],
+            position: 0,
+            record: 0,
+            eof: false,
+            error: None,
+        }
+    }
+
+    // Reads whole lines, so that no token is split, and at least as much as is buffered
+    // already, so that a value spanning many lines is not parsed over and over
+    fn fill(&mut self) {
+        self.buffer.drain(..self.position);
+        self.position = 0;
+
+        let wanted = self.buffer.len().max(MIN_READ);
+        let mut read = 0;
+        while read < wanted {
+            match self.reader.read_until(b'\n', &mut self.buffer) {
+                Ok(0) => {
+                    self.eof = true;
+                    break;
+                }
+                Ok(n) => read += n,
+                Err(e) => {
+                    self.eof = true;
+                    self.error = Some(e.into());
+                    break;
+                }
+            }
+        }
+    }
+
+    // Moves past the malformed record at `position`, reading as much as it spans
+    fn skip_record(&mut self) {
+        loop {
+            match record_end(&self.buffer[self.position..]) {
+                Some(end) => {
+                    self.position += end;
+                    return;
+                }
+                None if !self.eof => self.fill(),
+                None => {
+                    self.position = self.buffer.len();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn next_record(&mut self) -> Option<Result<Value>> {
+        loop {
+            let (result, consumed) = {
+                let rest = &self.buffer[self.position..];
+                let mut values = serde_json::Deserializer::from_slice(rest).into_iter::<Value>();
+                let result = values.next();
+                (result, values.byte_offset())
+            };
+
+            match result {
+                Some(Ok(value)) => {
+                    self.position += consumed;
+                    return Some(Ok(value));
+                }
+                Some(Err(ref e)) if e.is_eof() && !self.eof => {}
+                None if !self.eof => {}
+                None => return self.error.take().map(Err),
+                Some(Err(e)) => {
+                    self.skip_record();
+                    return Some(Err(e.into()));
+                }
+            }
+
+            self.fill();
+        }
+    }
+}
+
+// Length of the malformed record at the start of `bytes`, tracking brackets and strings
+// up to the end of its first line. A record failing on a later line did not close on its
+// first one, so the records on the following lines are read again; a newline in a string
+// is invalid anyway. `None` if the line does not end within `bytes`.
+fn record_end(bytes: &[u8]) -> Option<usize> {
+    let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+    match bytes[start] {
+        b'{' | b'[' | b'"' => {}
+        // A misspelled scalar or other garbage
+        _ => {
+            return bytes[start..]
+                .iter()
+                .position(|&byte| byte == b'\n' || byte == b'{' || byte == b'[')
+                .map(|length| start + length);
+        }
+    }
+
+    let mut closing = vec![];
+    let mut string = false;
+    let mut escaped = false;
+    for (offset, &byte) in bytes.iter().enumerate().skip(start) {
+        if byte == b'\n' {
+            return Some(offset + 1);
+        }
+        if string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => {
+                    string = false;
+                    if closing.is_empty() {
+                        return Some(offset + 1);
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => string = true,
+            b'{' => closing.push(b'}'),
+            b'[' => closing.push(b']'),
+            b'}' | b']' => {
+                let expected = closing.pop();
+                // A mismatched bracket ends the record as well
+                if expected != Some(byte) || closing.is_empty() {
+                    return Some(offset + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+impl<'s, R: BufRead> Iterator for Records<'s, R> {
+    type Item = (usize, Result<Vec<Value>>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = self.next_record()?;
+        self.record += 1;
+        let matches = record.map(|value| self.selector.find(&value).cloned().collect());
+        Some((self.record, matches))
+    }
+}
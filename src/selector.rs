@@ -1,22 +1,415 @@
-use serde_json::value::Value;
+#[cfg(feature = "serde")]
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::str::FromStr;
 
+use ast::Path;
+use complexity::{Complexity, ComplexityLimits};
+use document::Document;
 use errors::*;
-use iter::Iter;
+use index::Searchable;
+use iter::{Iter, LimitedIter, StrictIter};
+use limits::EvalLimits;
+#[cfg(feature = "rayon")]
+use parallel;
 use parser::parse;
-use structs::Criterion;
+use plan::{compile, Plan};
+use raw;
+use records::Records;
+use stream::{self, StreamMatch};
+use structs::{Criteria, Criterion};
+use trace::{self, Trace};
 
+/// A compiled JSONPath expression.
+///
+/// A selector displays as its canonical expression, which parses back to the same
+/// selector: `..` and brackets are spelled the shortest way, numbers without trailing
+/// zeros, and comparison operators and `&&`/`||` with a single space around them.
+/// Selectors are equal, and hash the same, when their canonical expressions are, so
+/// they can be logged and used as keys of caches.
+///
+/// ```
+/// # use jsonpath::Selector;
+/// let selector: Selector = "$.books[?(@.price<10.0)]".parse().unwrap();
+/// assert_eq!(selector.to_string(), "$.books[?(@.price < 10)]");
+/// assert_eq!(selector, Selector::new("$.books[?(@.price <  10)]").unwrap());
+/// ```
+#[derive(Clone)]
 pub struct Selector {
-    criteria: Vec<Criterion>,
+    plan: Plan,
+    canonical: String,
+    path: Path,
 }
 
 impl Selector {
     pub fn new(expression: &str) -> Result<Self> {
         let criteria = parse(expression)?;
-        let selector = Self { criteria };
+        let path = Path::from_criteria(&criteria)?;
+        Self::from_criteria(&criteria, path)
+    }
+
+    pub(crate) fn from_criteria(criteria: &[Criterion], path: Path) -> Result<Self> {
+        let plan = compile(criteria)?;
+        let canonical = Criteria(criteria).to_string();
+        Ok(Self {
+            plan,
+            canonical,
+            path,
+        })
+    }
+
+    /// The syntax tree of the selector, to inspect it or build another selector from it.
+    ///
+    /// ```
+    /// # use jsonpath::Selector;
+    /// use jsonpath::ast::{Path, Segment};
+    ///
+    /// let selector = Selector::new("$.books[0]").unwrap();
+    /// let path = selector.to_path();
+    /// assert_eq!(path.segments[1], Segment::Index(0));
+    /// assert_eq!(path.child("title").to_string(), "$.books[0].title");
+    /// ```
+    pub fn to_path(&self) -> Path {
+        self.path.clone()
+    }
+
+    /// Like `new`, but rejects selectors whose `complexity` exceeds `limits` with
+    /// `ErrorKind::TooComplex`, before they are ever evaluated.
+    ///
+    /// ```
+    /// # use jsonpath::{ComplexityLimits, Selector};
+    /// let limits = ComplexityLimits {
+    ///     max_filter_depth: Some(1),
+    ///     ..ComplexityLimits::default()
+    /// };
+    /// assert!(Selector::with_limits("$.books[?(@.price < 10)]", &limits).is_ok());
+    ///
+    /// let expression = "$.a[?(@.b == $.c[?(@.d == 1)].b)]";
+    /// let err = Selector::with_limits(expression, &limits).err().unwrap();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "selector too complex: filter depth is 2, at most 1 allowed"
+    /// );
+    /// ```
+    pub fn with_limits(expression: &str, limits: &ComplexityLimits) -> Result<Self> {
+        let selector = Self::new(expression)?;
+        selector.complexity().check(limits)?;
         Ok(selector)
     }
 
-    pub fn find<'a, 'b>(&'b self, root: &'a Value) -> Iter<'a, 'b> {
-        Iter::new(root, &self.criteria)
+    /// Static cost indicators of the selector, known without evaluating it.
+    ///
+    /// ```
+    /// # use jsonpath::Selector;
+    /// let selector = Selector::new("$..books[?(@.price > $.limits.price)].title").unwrap();
+    /// let complexity = selector.complexity();
+    /// assert_eq!(complexity.filter_depth, 1);
+    /// assert_eq!(complexity.descendants, 1);
+    /// assert_eq!(complexity.absolute_paths, 1);
+    /// ```
+    pub fn complexity(&self) -> Complexity {
+        Complexity::of(&self.plan)
+    }
+
+    /// Finds the matches in `root`, which may be a `serde_json::Value` or any other
+    /// implementation of `Document`, or an `IndexedDocument` of one.
+    pub fn find<'a, 'b, S: Searchable<'a>>(&'b self, root: S) -> Iter<'a, 'b, S::Document> {
+        Iter::new(root.document(), &self.plan.segments).indexed(root.index())
+    }
+
+    /// Like `find`, within `limits`: the matches are yielded until a limit is hit, then
+    /// `ErrorKind::LimitExceeded` ends the iteration. Meant for selectors written by
+    /// untrusted users, which could otherwise take unbounded time, e.g. with nested filters
+    /// and absolute paths.
+    ///
+    /// ```
+    /// # extern crate jsonpath;
+    /// # #[macro_use] extern crate serde_json;
+    /// # fn main() {
+    /// use jsonpath::{EvalLimits, Selector};
+    ///
+    /// let value = json!({"items": [1, 2, 3, 4, 5]});
+    /// let selector = Selector::new("$.items.*").unwrap();
+    /// let limits = EvalLimits {
+    ///     max_results: Some(3),
+    ///     ..EvalLimits::default()
+    /// };
+    ///
+    /// let found: Vec<_> = selector.find_limited(&value, &limits).collect();
+    /// assert_eq!(found.len(), 4);
+    /// assert_eq!(
+    ///     found[3].as_ref().unwrap_err().to_string(),
+    ///     "evaluation limit exceeded: more than 3 results"
+    /// );
+    /// # }
+    /// ```
+    pub fn find_limited<'a, 'b, S: Searchable<'a>>(
+        &'b self,
+        root: S,
+        limits: &EvalLimits,
+    ) -> LimitedIter<'a, 'b, S::Document> {
+        LimitedIter::new(self.find(root), limits, self.plan.filter_depth())
+    }
+
+    /// Like `find`, on the threads of rayon's global pool. Wildcards, slices and filters
+    /// are split across the threads, and the matches are returned in the order `find`
+    /// yields them.
+    ///
+    /// Paths in filters that start from `$` are evaluated once per job of the pool rather
+    /// than once per query.
+    #[cfg(feature = "rayon")]
+    pub fn par_find<'a, D: Document + Sync>(&self, root: &'a D) -> Vec<&'a D> {
+        parallel::find(&self.plan.segments, root)
+    }
+
+    /// Like `find`, but reports where the document does not have the shape the selector
+    /// expects instead of silently skipping it.
+    ///
+    /// Errors are yielded in between the matches, each with the normalized path where it
+    /// happened:
+    ///
+    /// * `ErrorKind::TypeMismatch` - e.g. `[3]` applied to a string, or a number compared
+    ///   with a string in a filter,
+    /// * `ErrorKind::MissingMember` - a named child or an index that does not exist, `[3]`
+    ///   applied to an object without the integer key `3` included,
+    /// * `ErrorKind::NonSingular` - a comparison operand that does not resolve to exactly
+    ///   one value.
+    pub fn find_strict<'a, 'b, D: Document>(&'b self, root: &'a D) -> StrictIter<'a, 'b, D> {
+        StrictIter::new(root, &self.plan.segments, "$")
+    }
+
+    /// Evaluates the selector while parsing the JSON document read from `reader`, without
+    /// building it as a `Value`. Matches are passed to `emit` as they are read, each one
+    /// materialised on its own; everything else is skipped as it is read.
+    ///
+    /// The order and the duplicates of the matches differ from `find` for selectors with
+    /// `..`: `stream` emits every matched value once, in document order, which is the
+    /// order of `find(..).unique()` sorted by position in the document. `find` applies the
+    /// segment after `..` to every descendant in turn and yields a value once per path
+    /// reaching it, so for `{"a": {"b": 1}, "c": 2}` `$..*` finds `{"b": 1}`, `2`, `1`
+    /// but streams `{"b": 1}`, `1`, `2`. Matching that order would mean holding back
+    /// every match until the end of the enclosing value. Without `..`, both give the
+    /// same matches in the same order.
+    ///
+    /// Named children, indexes, slices, wildcards and descendants are supported, filters
+    /// are reported as `ErrorKind::Unsupported`. Invalid JSON is reported as
+    /// `ErrorKind::Json`, after the matches found before it. `reader` is read byte by
+    /// byte, wrap it in a `BufReader` if it is not buffered.
+    ///
+    /// ```
+    /// # use jsonpath::Selector;
+    /// let selector = Selector::new("$..price").unwrap();
+    /// let json = r#"{"books": [{"price": 8.95}, {"price": 12.99}], "bicycle": {"price": 19.95}}"#;
+    ///
+    /// let mut paths = vec![];
+    /// selector
+    ///     .stream(json.as_bytes(), |found| paths.push(found.path))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     paths,
+    ///     vec!["$['books'][0]['price']", "$['books'][1]['price']", "$['bicycle']['price']"]
+    /// );
+    /// ```
+    pub fn stream<R, F>(&self, reader: R, emit: F) -> Result<()>
+    where
+        R: io::Read,
+        F: FnMut(StreamMatch),
+    {
+        stream::from_reader(&self.plan.segments, reader, emit)
+    }
+
+    /// Like `stream`, for a document in memory.
+    pub fn stream_slice<F>(&self, bytes: &[u8], emit: F) -> Result<()>
+    where
+        F: FnMut(StreamMatch),
+    {
+        stream::from_slice(&self.plan.segments, bytes, emit)
+    }
+
+    /// Evaluates the selector on the JSON text `json` without building it as a `Value`:
+    /// members and elements the selector cannot reach are skipped as they are parsed,
+    /// without being allocated. The matches are deserialized as `T`, which may borrow from
+    /// `json`: `&RawValue` gives the text of every match, `Value` parses it.
+    ///
+    /// Matches are in document order, as with `stream`. A candidate of a filter is parsed
+    /// before the filter is evaluated on it; absolute paths in filters are reported as
+    /// `ErrorKind::Unsupported`. Invalid JSON is reported as `ErrorKind::Json`.
+    ///
+    /// ```
+    /// # extern crate jsonpath;
+    /// # extern crate serde_json;
+    /// # fn main() {
+    /// use jsonpath::Selector;
+    /// use serde_json::value::RawValue;
+    /// use serde_json::Value;
+    ///
+    /// let json = r#"{"header": {"id": "a1", "tags": [1, 2]}, "body": [{"large": true}]}"#;
+    /// let selector = Selector::new("$.header.*").unwrap();
+    ///
+    /// let raw: Vec<&RawValue> = selector.find_in_str(json).unwrap();
+    /// assert_eq!(raw[1].get(), "[1, 2]");
+    ///
+    /// let values: Vec<Value> = selector.find_in_str(json).unwrap();
+    /// assert_eq!(values[0], "a1");
+    /// # }
+    /// ```
+    pub fn find_in_str<'a, T: Deserialize<'a>>(&self, json: &'a str) -> Result<Vec<T>> {
+        raw::find_in_str(&self.plan.segments, json)
+    }
+
+    /// Like `find_in_str`, for JSON text that has not been checked to be UTF-8.
+    pub fn find_in_slice<'a, T: Deserialize<'a>>(&self, bytes: &'a [u8]) -> Result<Vec<T>> {
+        raw::find_in_slice(&self.plan.segments, bytes)
+    }
+
+    /// Applies the selector to every record of a JSON Lines (NDJSON) or concatenated JSON
+    /// stream, yielding `(record_number, matches)`. Malformed records are reported and
+    /// skipped, see `Records`.
+    ///
+    /// ```
+    /// # use jsonpath::Selector;
+    /// let selector = Selector::new("$.level").unwrap();
+    /// let log = "{\"level\": \"info\"}\n{\"level\" 3}\n{\"level\": \"warn\"} {}\n";
+    ///
+    /// let records: Vec<_> = selector.find_records(log.as_bytes()).collect();
+    /// assert_eq!(records.len(), 4);
+    /// assert_eq!(records[0].1.as_ref().unwrap(), &vec!["info"]);
+    /// assert!(records[1].1.is_err());
+    /// assert_eq!(records[2].1.as_ref().unwrap(), &vec!["warn"]);
+    /// assert!(records[3].1.as_ref().unwrap().is_empty());
+    /// ```
+    pub fn find_records<'s, R: io::BufRead>(&'s self, reader: R) -> Records<'s, R> {
+        Records::new(self, reader)
+    }
+
+    /// Evaluates the selector on `root` like `find`, recording every segment tested against
+    /// every node and its outcome: the values selected, or whether a filter accepted the
+    /// node along with the values it compared.
+    ///
+    /// ```
+    /// # extern crate jsonpath;
+    /// # #[macro_use] extern crate serde_json;
+    /// # fn main() {
+    /// use jsonpath::Selector;
+    ///
+    /// let value = json!({"books": [{"price": 8.95}, {"price": 12.99}]});
+    /// let selector = Selector::new("$.books[?(@.price > 10)]").unwrap();
+    /// let trace = selector.trace(&value);
+    ///
+    /// assert_eq!(trace.matches, vec![&value["books"][1]]);
+    /// assert_eq!(
+    ///     trace.to_string(),
+    ///     "$: root -> $\n\
+    ///      $: member \"books\" -> $['books']\n\
+    ///      $['books'][0]: filter -> rejected\n\
+    ///      \x20 [8.95] > [10.0] -> false\n\
+    ///      $['books'][1]: filter -> matched\n\
+    ///      \x20 [12.99] > [10.0] -> true\n"
+    /// );
+    /// # }
+    /// ```
+    pub fn trace<'a>(&self, root: &'a Value) -> Trace<'a> {
+        trace::trace(&self.plan.segments, root)
+    }
+
+    /// Describes the execution plan the expression was compiled into, one step per line.
+    ///
+    /// ```
+    /// # use jsonpath::Selector;
+    /// let selector = Selector::new("$.books[?(@.price < 10)].title").unwrap();
+    /// assert_eq!(
+    ///     selector.explain(),
+    ///     "root\n\
+    ///      member \"books\"\n\
+    ///      filter\n\
+    ///      \x20 compare <\n\
+    ///      \x20   current\n\
+    ///      \x20   member \"price\"\n\
+    ///      \x20   number 10\n\
+    ///      member \"title\"\n"
+    /// );
+    /// ```
+    pub fn explain(&self) -> String {
+        self.plan.to_string()
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.canonical)
+    }
+}
+
+impl fmt::Debug for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("Selector").field(&self.canonical).finish()
+    }
+}
+
+impl FromStr for Selector {
+    type Err = Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        Selector::new(expression)
+    }
+}
+
+impl PartialEq for Selector {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical == other.canonical
+    }
+}
+
+impl Eq for Selector {}
+
+impl Hash for Selector {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical.hash(state);
+    }
+}
+
+/// With the `serde` feature, a selector is serialized as its canonical expression.
+#[cfg(feature = "serde")]
+impl Serialize for Selector {
+    fn serialize<S: Serializer>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.canonical)
+    }
+}
+
+/// With the `serde` feature, a selector is deserialized from an expression, which is
+/// parsed on the spot. Invalid expressions fail the deserialization with the position of
+/// the problem, e.g. ``invalid JSONPath expression `$.a[`: unexpected end of expression at
+/// line 1, column 5, expected ...``.
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> ::std::result::Result<Self, D::Error> {
+        deserializer.deserialize_str(ExpressionVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct ExpressionVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> Visitor<'de> for ExpressionVisitor {
+    type Value = Selector;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSONPath expression")
+    }
+
+    fn visit_str<E: de::Error>(self, expression: &str) -> ::std::result::Result<Selector, E> {
+        Selector::new(expression).map_err(|e| {
+            E::custom(format_args!(
+                "invalid JSONPath expression `{}`: {}",
+                expression, e
+            ))
+        })
     }
 }
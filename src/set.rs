@@ -0,0 +1,263 @@
+use document::Document;
+use errors::*;
+use filter::{self, SharedCache};
+use iter::Children;
+use parser::parse;
+use plan::{Compiler, Segment};
+use structs::Criterion;
+
+/// A set of selectors evaluated together in a single pass over a document.
+///
+/// Like `regex::RegexSet`, but for JSONPath: selectors that start with the same
+/// segments share them, so `$.payload.user.name` and `$.payload.user.id` walk
+/// `$.payload.user` once.
+///
+/// ```
+/// # extern crate jsonpath;
+/// # #[macro_use] extern crate serde_json;
+/// # fn main() {
+/// use jsonpath::SelectorSet;
+///
+/// let set = SelectorSet::new(["$.user.name", "$.user.id", "$.tags.*"]).unwrap();
+/// let event = json!({"user": {"name": "Jane"}, "tags": ["a", "b"]});
+///
+/// let matches = set.matches(&event);
+/// assert_eq!(matches.iter().collect::<Vec<usize>>(), vec![0, 2]);
+///
+/// let found = set.find(&event);
+/// assert_eq!(found[0], vec!["Jane"]);
+/// assert!(found[1].is_empty());
+/// assert_eq!(found[2], vec!["a", "b"]);
+/// # }
+/// ```
+pub struct SelectorSet {
+    // Trie of the segments, kept flat so that long selectors do not need deep recursion
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    len: usize,
+}
+
+// A segment shared by all selectors whose criteria start with the criteria of the
+// ancestors and this node
+struct Node {
+    criterion: Criterion,
+    segment: Segment,
+    children: Vec<usize>,
+    // Selectors ending with this segment
+    ends: Vec<usize>,
+    // Selectors ending with this segment or below
+    below: Vec<usize>,
+}
+
+/// Which selectors of a `SelectorSet` matched a document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetMatches {
+    matched: Vec<bool>,
+}
+
+impl SelectorSet {
+    /// Compiles the expressions into a set, failing on the first invalid one.
+    pub fn new<I, S>(expressions: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut compiler = Compiler::default();
+        let mut set = Self {
+            nodes: vec![],
+            roots: vec![],
+            len: 0,
+        };
+        for expression in expressions {
+            let criteria = parse(expression.as_ref())?;
+            set.insert(&criteria, &mut compiler)?;
+        }
+        Ok(set)
+    }
+
+    fn insert(&mut self, criteria: &[Criterion], compiler: &mut Compiler) -> Result<()> {
+        let index = self.len;
+        self.len += 1;
+
+        let mut parent: Option<usize> = None;
+        for (depth, criterion) in criteria.iter().enumerate() {
+            let siblings = match parent {
+                Some(parent) => &self.nodes[parent].children,
+                None => &self.roots,
+            };
+            let existing = siblings
+                .iter()
+                .cloned()
+                .find(|&id| self.nodes[id].criterion == *criterion);
+
+            let id = match existing {
+                Some(id) => id,
+                None => {
+                    let id = self.nodes.len();
+                    self.nodes.push(Node {
+                        criterion: criterion.clone(),
+                        segment: compiler.segment(criterion)?,
+                        children: vec![],
+                        ends: vec![],
+                        below: vec![],
+                    });
+                    match parent {
+                        Some(parent) => self.nodes[parent].children.push(id),
+                        None => self.roots.push(id),
+                    }
+                    id
+                }
+            };
+
+            let node = &mut self.nodes[id];
+            node.below.push(index);
+            if depth + 1 == criteria.len() {
+                node.ends.push(index);
+            }
+            parent = Some(id);
+        }
+        Ok(())
+    }
+
+    /// Number of selectors in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set has no selectors.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Matches of every selector, indexed like the expressions the set was created from.
+    /// The matches of each selector are the ones `Selector::find` gives, in the same order.
+    pub fn find<'a, D: Document>(&self, root: &'a D) -> Vec<Vec<&'a D>> {
+        self.walk(root, false)
+    }
+
+    /// Which selectors match at least once. Subtrees are skipped as soon as all
+    /// selectors going through them have matched.
+    pub fn matches<D: Document>(&self, root: &D) -> SetMatches {
+        let matched = self
+            .walk(root, true)
+            .iter()
+            .map(|found| !found.is_empty())
+            .collect();
+        SetMatches { matched }
+    }
+
+    fn walk<'a, D: Document>(&self, root: &'a D, first_only: bool) -> Vec<Vec<&'a D>> {
+        let mut found: Vec<Vec<&'a D>> = vec![vec![]; self.len];
+        let cache = SharedCache::default();
+
+        let pending = |node: &Node, found: &[Vec<&D>]| {
+            !first_only || node.below.iter().any(|&index| found[index].is_empty())
+        };
+
+        let mut stack: Vec<(&Node, Children<'a, D>)> = vec![];
+        for &id in self.roots.iter().rev() {
+            let node = &self.nodes[id];
+            stack.push((node, Children::new(root, &node.segment)));
+        }
+
+        while let Some((node, value)) = next(&mut stack) {
+            if !pending(node, &found) {
+                continue;
+            }
+            if let Segment::Filter(ref expr) = node.segment {
+                if !filter::matches(expr, value, root, None, &cache).unwrap_or(false) {
+                    continue;
+                }
+            }
+
+            // As in `Iter`, the children of a value matched by `..` are matched by it too
+            if let Segment::Descendant = node.segment {
+                stack.push((node, Children::all(value)));
+            }
+            for &index in &node.ends {
+                if !first_only || found[index].is_empty() {
+                    found[index].push(value);
+                }
+            }
+            // Reversed, so that the children are walked in the order of the selectors
+            for &id in node.children.iter().rev() {
+                let child = &self.nodes[id];
+                if pending(child, &found) {
+                    stack.push((child, Children::new(value, &child.segment)));
+                }
+            }
+        }
+        found
+    }
+}
+
+// Next candidate of the innermost segment that still has candidates
+fn next<'a, 'n, D: Document>(
+    stack: &mut Vec<(&'n Node, Children<'a, D>)>,
+) -> Option<(&'n Node, &'a D)> {
+    loop {
+        let node = {
+            let &mut (node, ref mut children) = stack.last_mut()?;
+            children.next().map(|(value, _)| (node, value))
+        };
+        match node {
+            Some(node) => return Some(node),
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+impl SetMatches {
+    /// Whether the selector at `index` matched.
+    pub fn matched(&self, index: usize) -> bool {
+        self.matched.get(index).cloned().unwrap_or(false)
+    }
+
+    /// Whether any selector matched.
+    pub fn matched_any(&self) -> bool {
+        self.matched.iter().any(|&matched| matched)
+    }
+
+    /// Indexes of the selectors that matched.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.matched
+            .iter()
+            .enumerate()
+            .filter(|&(_, &matched)| matched)
+            .map(|(index, _)| index)
+    }
+
+    /// Number of selectors in the set.
+    pub fn len(&self) -> usize {
+        self.matched.len()
+    }
+
+    /// Whether the set had no selectors.
+    pub fn is_empty(&self) -> bool {
+        self.matched.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_common_prefixes_are_shared() {
+        let set = SelectorSet::new([
+            "$.payload.user.name",
+            "$.payload.user.id",
+            "$.payload.items[0]",
+            "$.payload",
+        ])
+        .unwrap();
+
+        // $, payload, user, name, id, items, [0]
+        assert_eq!(set.nodes.len(), 7);
+        assert_eq!(set.roots, vec![0]);
+        assert_eq!(set.nodes[1].ends, vec![3]);
+        assert_eq!(set.nodes[1].below, vec![0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,313 @@
+//! Evaluation of a selector while a document is being parsed.
+//!
+//! The segments of the plan are run as a set of states, one per segment that still has to
+//! be matched, so that the document is never built as a whole: members and elements no
+//! state can match are skipped without being stored, and only matched values are
+//! materialised as `Value`s.
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::io;
+
+use errors::*;
+use plan::Segment;
+use structs::Step;
+
+/// A value matched by streaming evaluation, with its normalized path, e.g.
+/// `$['store']['books'][0]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamMatch {
+    pub path: String,
+    pub value: Value,
+}
+
+pub fn from_reader<R, F>(segments: &[Segment], reader: R, emit: F) -> Result<()>
+where
+    R: io::Read,
+    F: FnMut(StreamMatch),
+{
+    check(segments)?;
+    let mut deserializer = ::serde_json::Deserializer::from_reader(reader);
+    run(segments, &mut deserializer, emit)?;
+    deserializer.end()?;
+    Ok(())
+}
+
+pub fn from_slice<F>(segments: &[Segment], bytes: &[u8], emit: F) -> Result<()>
+where
+    F: FnMut(StreamMatch),
+{
+    check(segments)?;
+    let mut deserializer = ::serde_json::Deserializer::from_slice(bytes);
+    run(segments, &mut deserializer, emit)?;
+    deserializer.end()?;
+    Ok(())
+}
+
+// Filters need the whole of the filtered values, and `$` in them the whole document
+fn check(segments: &[Segment]) -> Result<()> {
+    for segment in segments {
+        if let Segment::Filter(_) = *segment {
+            bail!(ErrorKind::Unsupported(
+                "filters in streaming evaluation".to_owned()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn run<'de, D, F>(
+    segments: &[Segment],
+    deserializer: D,
+    mut emit: F,
+) -> ::std::result::Result<(), D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnMut(StreamMatch),
+{
+    let mut walker = Walker {
+        segments,
+        path: "$".to_owned(),
+        emit: &mut emit,
+    };
+    // The root is matched by the first segment, `$`
+    let states = closure(segments, vec![1]);
+    walker.node(deserializer, states)
+}
+
+struct Walker<'s> {
+    segments: &'s [Segment],
+    // Normalized path of the value being parsed
+    path: String,
+    emit: &'s mut dyn FnMut(StreamMatch),
+}
+
+// A state `i` means that the segments before `i` have been matched. A value matched by
+// `..` is also matched by the segment after it.
+pub fn closure(segments: &[Segment], mut states: Vec<usize>) -> Vec<usize> {
+    let mut i = 0;
+    while i < states.len() {
+        if let Some(&Segment::Descendant) = segments.get(states[i]) {
+            let next = states[i] + 1;
+            if !states.contains(&next) {
+                states.push(next);
+            }
+        }
+        i += 1;
+    }
+    states
+}
+
+// States of the child at `step` of a value in `states`. Filters are left to the caller.
+pub fn advance(segments: &[Segment], states: &[usize], step: &Step) -> Vec<usize> {
+    let mut next = vec![];
+    for &state in states {
+        let matched = match (segments.get(state), step) {
+            (Some(&Segment::Descendant), _) => {
+                if !next.contains(&state) {
+                    next.push(state);
+                }
+                continue;
+            }
+            (Some(Segment::Member(name)), Step::Key(key)) => name == key,
+            (Some(&Segment::Index(index)), &Step::Index(i)) => index == i,
+            (Some(&Segment::Slice(start, end)), &Step::Index(i)) => start <= i && i < end,
+            (Some(&Segment::Wildcard), _) => true,
+            _ => false,
+        };
+        if matched && !next.contains(&(state + 1)) {
+            next.push(state + 1);
+        }
+    }
+    closure(segments, next)
+}
+
+pub fn is_match(segments: &[Segment], states: &[usize]) -> bool {
+    states.contains(&segments.len())
+}
+
+impl<'s> Walker<'s> {
+    fn step(&self, states: &[usize], step: &Step) -> Vec<usize> {
+        advance(self.segments, states, step)
+    }
+
+    fn is_match(&self, states: &[usize]) -> bool {
+        is_match(self.segments, states)
+    }
+
+    fn node<'de, D>(
+        &mut self,
+        deserializer: D,
+        states: Vec<usize>,
+    ) -> ::std::result::Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !self.is_match(&states) {
+            return deserializer.deserialize_any(NodeVisitor {
+                walker: self,
+                states,
+            });
+        }
+
+        // Matches inside a matched value are found in the materialised value, and
+        // emitted after it
+        let value = Value::deserialize(deserializer)?;
+        let mut inner = vec![];
+        let mut path = self.path.clone();
+        self.walk_value(&value, &states, &mut path, &mut inner);
+        (self.emit)(StreamMatch {
+            path: self.path.clone(),
+            value,
+        });
+        for found in inner {
+            (self.emit)(found);
+        }
+        Ok(())
+    }
+
+    fn walk_value(
+        &self,
+        value: &Value,
+        states: &[usize],
+        path: &mut String,
+        found: &mut Vec<StreamMatch>,
+    ) {
+        let children: Vec<(Step, &Value)> = match *value {
+            Value::Array(ref vec) => vec
+                .iter()
+                .enumerate()
+                .map(|(index, child)| (Step::Index(index), child))
+                .collect(),
+            Value::Object(ref map) => map
+                .iter()
+                .map(|(key, child)| (Step::Key(key), child))
+                .collect(),
+            _ => return,
+        };
+
+        for (step, child) in children {
+            let child_states = self.step(states, &step);
+            if child_states.is_empty() {
+                continue;
+            }
+            let len = path.len();
+            path.push_str(&step.to_string());
+            if self.is_match(&child_states) {
+                found.push(StreamMatch {
+                    path: path.clone(),
+                    value: child.clone(),
+                });
+            }
+            self.walk_value(child, &child_states, path, found);
+            path.truncate(len);
+        }
+    }
+}
+
+struct NodeSeed<'w, 's: 'w> {
+    walker: &'w mut Walker<'s>,
+    states: Vec<usize>,
+}
+
+impl<'de, 'w, 's> DeserializeSeed<'de> for NodeSeed<'w, 's> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> ::std::result::Result<(), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.walker.node(deserializer, self.states)
+    }
+}
+
+struct NodeVisitor<'w, 's: 'w> {
+    walker: &'w mut Walker<'s>,
+    states: Vec<usize>,
+}
+
+impl<'de, 'w, 's> Visitor<'de> for NodeVisitor<'w, 's> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_bool<E: de::Error>(self, _: bool) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_i64<E: de::Error>(self, _: i64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_u64<E: de::Error>(self, _: u64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_f64<E: de::Error>(self, _: f64) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_unit<E: de::Error>(self) -> ::std::result::Result<(), E> {
+        Ok(())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> ::std::result::Result<(), A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let walker = self.walker;
+        let mut index = 0;
+        loop {
+            let step = Step::Index(index);
+            let states = walker.step(&self.states, &step);
+            let more = if states.is_empty() {
+                seq.next_element::<IgnoredAny>()?.is_some()
+            } else {
+                let len = walker.path.len();
+                walker.path.push_str(&step.to_string());
+                let more = seq
+                    .next_element_seed(NodeSeed {
+                        walker: &mut *walker,
+                        states,
+                    })?
+                    .is_some();
+                walker.path.truncate(len);
+                more
+            };
+            if !more {
+                return Ok(());
+            }
+            index += 1;
+        }
+    }
+
+    fn visit_map<A>(self, mut map: A) -> ::std::result::Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let walker = self.walker;
+        while let Some(key) = map.next_key::<String>()? {
+            let step = Step::Key(&key);
+            let states = walker.step(&self.states, &step);
+            if states.is_empty() {
+                map.next_value::<IgnoredAny>()?;
+                continue;
+            }
+            let len = walker.path.len();
+            walker.path.push_str(&step.to_string());
+            map.next_value_seed(NodeSeed {
+                walker: &mut *walker,
+                states,
+            })?;
+            walker.path.truncate(len);
+        }
+        Ok(())
+    }
+}
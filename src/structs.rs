@@ -1,7 +1,8 @@
-use filter;
-use serde_json::Value;
-use std::iter::Enumerate;
-use std::slice::Iter;
+use document::{Document, Key, Kind};
+use errors::*;
+use plan::Segment;
+use regex::Regex;
+use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Criterion {
@@ -23,9 +24,18 @@ pub enum Criterion {
     // .*
     AnyChild,
 
+    // .., followed by the criterion applied to the value and all its descendants
+    Descendant,
+
     // [123]
     IndexedChild(usize),
 
+    // [-1], a negative integer key
+    IntegerChild(i128),
+
+    // [h'cafe']
+    BytesChild(Vec<u8>),
+
     // [10:20]
     Slice(::std::ops::Range<usize>),
 
@@ -56,6 +66,12 @@ pub enum Criterion {
     // <=
     LowerOrEqual,
 
+    // =~
+    Matches,
+
+    // /pattern/flags
+    Regex(Pattern),
+
     // 'content'
     Literal(String),
 
@@ -69,132 +85,250 @@ pub enum Criterion {
     Or,
 }
 
+// The source of a regular expression, with the `i` flag turned into an inline flag, and
+// the expression compiled from it when the parser validated it already
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub source: String,
+    pub regex: Option<Regex>,
+}
+
+impl Pattern {
+    pub fn new(source: String) -> Self {
+        Pattern {
+            source,
+            regex: None,
+        }
+    }
+
+    pub fn compile(&self) -> Result<Regex> {
+        match self.regex {
+            Some(ref regex) => Ok(regex.clone()),
+            None => Regex::new(&self.source).map_err(|e| ErrorKind::Compile(e.to_string()).into()),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+// Renders criteria back to an expression that parses to the same criteria: the canonical
+// spelling of a selector, with a single space around comparison operators and `&&`/`||`
+pub struct Criteria<'a>(pub &'a [Criterion]);
+
+impl<'a> fmt::Display for Criteria<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut after_descendant = false;
+        for criterion in self.0 {
+            match *criterion {
+                Criterion::Root => write!(f, "$")?,
+                Criterion::Element => write!(f, "@")?,
+                Criterion::NamedChild(ref name) if !is_ident(name) => {
+                    write!(f, "[{}]", Quoted(name))?
+                }
+                // `..name` and `..*` rather than `...name` and `...*`
+                Criterion::NamedChild(ref name) if after_descendant => write!(f, "{}", name)?,
+                Criterion::NamedChild(ref name) => write!(f, ".{}", name)?,
+                Criterion::AnyChild if after_descendant => write!(f, "*")?,
+                Criterion::AnyChild => write!(f, ".*")?,
+                Criterion::Descendant => write!(f, "..")?,
+                Criterion::IndexedChild(index) => write!(f, "[{}]", index)?,
+                Criterion::IntegerChild(key) => write!(f, "[{}]", key)?,
+                Criterion::BytesChild(ref bytes) => write!(f, "[{}]", Hex(bytes))?,
+                Criterion::Slice(ref range) => write!(f, "[{}:{}]", range.start, range.end)?,
+                Criterion::SliceTo(ref range_to) => write!(f, "[:{}]", range_to.end)?,
+                Criterion::SliceFrom(from) => write!(f, "[{}:]", from)?,
+                Criterion::Filter(ref criteria) => write!(f, "[?({})]", Criteria(criteria))?,
+                Criterion::SubExpression(ref criteria) => write!(f, "{}", Criteria(criteria))?,
+                Criterion::Array(ref items) => {
+                    // Numbers of an array are either all integers or all with a fraction
+                    let fractions = items.iter().any(|item| match *item {
+                        Criterion::Float(value) => value.fract() != 0.0,
+                        _ => false,
+                    });
+                    write!(f, "[")?;
+                    for (i, item) in items.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        match *item {
+                            Criterion::Float(value) if fractions && value.fract() == 0.0 => {
+                                write!(f, "{}.0", value)?
+                            }
+                            ref item => write!(f, "{}", Criteria(::std::slice::from_ref(item)))?,
+                        }
+                    }
+                    write!(f, "]")?;
+                }
+                Criterion::Equal => write!(f, " == ")?,
+                Criterion::Different => write!(f, " != ")?,
+                Criterion::Greater => write!(f, " > ")?,
+                Criterion::GreaterOrEqual => write!(f, " >= ")?,
+                Criterion::Lower => write!(f, " < ")?,
+                Criterion::LowerOrEqual => write!(f, " <= ")?,
+                Criterion::Matches => write!(f, " =~ ")?,
+                // The parser turns the `i` flag into an inline flag of the pattern
+                Criterion::Regex(ref pattern) => match pattern.source.strip_prefix("(?i)") {
+                    Some(source) => write!(f, "/{}/i", Slashed(source))?,
+                    None => write!(f, "/{}/", Slashed(&pattern.source))?,
+                },
+                Criterion::Literal(ref content) => write!(f, "{}", Quoted(content))?,
+                // Shortest spelling that parses back to the same number, `10` for `10.0`
+                Criterion::Float(value) => write!(f, "{}", value)?,
+                Criterion::And => write!(f, ") && ?(")?,
+                Criterion::Or => write!(f, ") || ?(")?,
+            }
+            after_descendant = *criterion == Criterion::Descendant;
+        }
+        Ok(())
+    }
+}
+
 // A step during traversing JSON tree
 #[derive(Debug, Clone, PartialEq)]
 pub enum Step<'a> {
     Root,
     Key(&'a str),
     Index(usize),
+    Integer(i128),
+    Bytes(&'a [u8]),
+    // Any other key, spelled as in the document
+    Other(String),
 }
 
-// TODO: write unit tests
-pub fn matches<'a>(stack: &mut StackItem, criterion: &Criterion, root: &StackItem<'a>) -> bool {
-    let step = stack.step.clone();
-    match *criterion {
-        Criterion::Root => match step {
-            Step::Root => true,
-            _ => false,
-        },
-        Criterion::Element => false,
-        Criterion::Equal => false,
-        Criterion::Different => false,
-        Criterion::Greater => false,
-        Criterion::GreaterOrEqual => false,
-        Criterion::Lower => false,
-        Criterion::LowerOrEqual => false,
-        Criterion::And => false,
-        Criterion::Or => false,
-        Criterion::Literal(ref _content) => false,
-        Criterion::Float(ref _value) => false,
-        Criterion::Array(ref _value) => false,
-        Criterion::SubExpression(ref _expr) => false,
-        Criterion::NamedChild(ref child_name) => match step {
-            Step::Key(key) => child_name == key,
-            _ => false,
-        },
-        Criterion::Filter(ref path) => {
-            let mut filter_stack = stack.clone();
-            filter::process_filter(&mut filter_stack, path, root)
+impl<'a> From<Key<'a>> for Step<'a> {
+    fn from(key: Key<'a>) -> Self {
+        match key {
+            Key::Name(name) => Step::Key(name),
+            Key::Integer(integer) => Step::Integer(integer),
+            Key::Bytes(bytes) => Step::Bytes(bytes),
+            Key::Other(key) => Step::Other(key),
         }
-        Criterion::AnyChild => match step {
-            Step::Key(_) => true,
-            Step::Index(_) => true,
-            _ => false,
-        },
-        Criterion::IndexedChild(index) => match step {
-            Step::Index(idx) => index == idx,
-            _ => false,
-        },
-        Criterion::Slice(ref range) => match step {
-            Step::Index(idx) => range.start <= idx && idx <= range.end,
-            _ => false,
-        },
-        Criterion::SliceTo(ref range_to) => match step {
-            Step::Index(idx) => idx < range_to.end,
-            _ => false,
-        },
-        Criterion::SliceFrom(from) => match step {
-            Step::Index(idx) => from <= idx,
-            _ => false,
-        },
     }
 }
 
-pub enum ItemIter<'a> {
-    Array(Enumerate<Iter<'a, Value>>),
-    Object(::serde_json::map::Iter<'a>),
-}
+// A name or a string literal between single quotes, `'` and `\` escaped with a backslash
+pub struct Quoted<'a>(pub &'a str);
 
-pub struct Item<'a> {
-    pub value: &'a Value,
-    pub iter: Option<ItemIter<'a>>,
+impl<'a> fmt::Display for Quoted<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'")?;
+        for c in self.0.chars() {
+            match c {
+                '\'' | '\\' => write!(f, "\\{}", c)?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "'")
+    }
 }
 
-impl<'a> Item<'a> {
-    pub fn new(value: &'a Value) -> Self {
-        let iter = match *value {
-            Value::Array(ref vec) => Some(ItemIter::Array(vec.iter().enumerate())),
-            Value::Object(ref map) => Some(ItemIter::Object(map.iter())),
-            _ => None,
-        };
-        Self { value, iter }
-    }
+// A regular expression between slashes, `/` escaped with a backslash where it is not yet
+pub struct Slashed<'a>(pub &'a str);
 
-    pub fn next(&mut self) -> Option<(Self, Step<'a>)> {
-        match self.iter {
-            Some(ref mut item_iter) => match *item_iter {
-                ItemIter::Array(ref mut iter) => match iter.next() {
-                    Some((index, val)) => {
-                        let sub_item = Item::new(val);
-                        let step = Step::Index(index);
-                        Some((sub_item, step))
-                    }
-                    None => None,
-                },
-                ItemIter::Object(ref mut iter) => match iter.next() {
-                    Some((key, val)) => {
-                        let sub_item = Item::new(val);
-                        let step = Step::Key(key);
-                        Some((sub_item, step))
+impl<'a> fmt::Display for Slashed<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chars = self.0.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    write!(f, "\\")?;
+                    if let Some(c) = chars.next() {
+                        write!(f, "{}", c)?;
                     }
-                    None => None,
-                },
-            },
-            None => None,
+                }
+                '/' => write!(f, "\\/")?,
+                c => write!(f, "{}", c)?,
+            }
         }
+        Ok(())
     }
 }
 
-impl<'a> Clone for Item<'a> {
-    fn clone(&self) -> Item<'a> {
-        Item::new(self.value)
-    }
+// Names that can follow a dot, as the `ident` rule of the grammar
+fn is_ident(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-/\\".contains(c))
 }
 
-#[derive(Clone)]
-pub struct StackItem<'a> {
-    pub item: Item<'a>,
-    pub step: Step<'a>,
+// Byte strings in the notation of CBOR diagnostics, `h'cafe'`
+pub struct Hex<'a>(pub &'a [u8]);
+
+impl<'a> fmt::Display for Hex<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "h'")?;
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        write!(f, "'")
+    }
 }
 
-impl<'a> StackItem<'a> {
-    pub fn new(item: Item<'a>, step: Step<'a>) -> Self {
-        Self { item, step }
+// Renders a step of a normalized path, e.g. `['name']` or `[3]`
+impl<'a> fmt::Display for Step<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Step::Root => write!(f, "$"),
+            Step::Key(key) => write!(f, "[{}]", Quoted(key)),
+            Step::Index(index) => write!(f, "[{}]", index),
+            Step::Integer(integer) => write!(f, "[{}]", integer),
+            Step::Bytes(bytes) => write!(f, "[{}]", Hex(bytes)),
+            Step::Other(ref key) => write!(f, "[{}]", key),
+        }
     }
+}
+
+// Verifies in strict mode that a value has the shape the next segment expects
+pub fn check_shape<D: Document>(value: &D, segment: &Segment, path: &str) -> Result<()> {
+    let missing = match (segment, value.kind()) {
+        (Segment::Member(name), Kind::Object) => match value.member(name) {
+            Some(_) => None,
+            None => Some(Step::Key(name)),
+        },
+        (Segment::Index(index), Kind::Array) => match value.element(*index) {
+            Some(_) => None,
+            None => Some(Step::Index(*index)),
+        },
+        // Maps of CBOR and MessagePack may have integer keys
+        (Segment::Index(index), Kind::Object) => {
+            match value.member_by_key(&Key::Integer(*index as i128)) {
+                Some(_) => None,
+                None => Some(Step::Integer(*index as i128)),
+            }
+        }
+        (Segment::Integer(key), Kind::Object) => match value.member_by_key(&Key::Integer(*key)) {
+            Some(_) => None,
+            None => Some(Step::Integer(*key)),
+        },
+        (Segment::Bytes(bytes), Kind::Object) => match value.member_by_key(&Key::Bytes(bytes)) {
+            Some(_) => None,
+            None => Some(Step::Bytes(bytes)),
+        },
+        (Segment::Slice(..), Kind::Array) => None,
+        (Segment::Member(_), found) | (Segment::Integer(_), found) | (Segment::Bytes(_), found) => {
+            bail!(ErrorKind::TypeMismatch(
+                path.to_owned(),
+                "object".to_owned(),
+                found.name().to_owned()
+            ))
+        }
+        (Segment::Index(_), found) | (Segment::Slice(..), found) => {
+            bail!(ErrorKind::TypeMismatch(
+                path.to_owned(),
+                "array".to_owned(),
+                found.name().to_owned()
+            ))
+        }
+        _ => None,
+    };
 
-    pub fn next(&mut self) -> Option<Self> {
-        self.item
-            .next()
-            .map(|(sub_item, step)| Self::new(sub_item, step))
+    match missing {
+        Some(step) => bail!(ErrorKind::MissingMember(path.to_owned(), step.to_string())),
+        None => Ok(()),
     }
 }
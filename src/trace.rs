@@ -0,0 +1,247 @@
+use serde_json::Value;
+use std::fmt;
+
+use document::Document;
+use filter::{self, SharedCache};
+use iter::Children;
+use plan::{Expr, Operand, Segment};
+use structs::Step;
+
+/// What `Selector::trace` recorded of an evaluation.
+///
+/// `Display` renders one line per step, with the comparisons of filters below them:
+///
+/// ```text
+/// $: member "books" -> $['books']
+/// $['books'][0]: filter -> rejected
+///   [8.95] > [10.0] -> false
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace<'a> {
+    /// Every segment tested against every node, in the order of evaluation.
+    pub steps: Vec<TraceStep>,
+
+    /// The matches, as `Selector::find` yields them.
+    pub matches: Vec<&'a Value>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceStep {
+    /// Normalized path of the node.
+    pub path: String,
+
+    /// The segment tested against the node, as in `Selector::explain`, e.g. `member "price"`.
+    pub segment: String,
+
+    pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Normalized paths of the values the segment selected from the node, none when e.g.
+    /// a named child is missing. `..` selects the children it descends into.
+    Selected(Vec<String>),
+
+    /// Whether a filter accepted the node, with the comparisons it evaluated to decide.
+    Filtered {
+        matched: bool,
+        comparisons: Vec<ComparisonTrace>,
+    },
+}
+
+/// A condition of a filter evaluated on a node.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComparisonTrace {
+    /// Values found by the path of the condition.
+    pub left: Vec<Value>,
+
+    /// `==`, `<`, `=~`... or `exists` for a path on its own.
+    pub operator: String,
+
+    /// Values compared with: a literal, the items of an array, the values found by an
+    /// absolute path or the pattern of a regular expression.
+    pub right: Vec<Value>,
+
+    pub result: bool,
+}
+
+pub fn trace<'a>(segments: &[Segment], root: &'a Value) -> Trace<'a> {
+    let mut tracer = Tracer {
+        segments,
+        root,
+        cache: SharedCache::default(),
+        trace: Trace {
+            steps: vec![],
+            matches: vec![],
+        },
+    };
+    if !segments.is_empty() {
+        tracer.walk(0, root, "$".to_owned());
+    }
+    tracer.trace
+}
+
+struct Tracer<'a, 's> {
+    segments: &'s [Segment],
+    root: &'a Value,
+    cache: SharedCache<'a, Value>,
+    trace: Trace<'a>,
+}
+
+impl<'a, 's> Tracer<'a, 's> {
+    fn step(&mut self, path: &str, segment: &Segment, outcome: Outcome) {
+        self.trace.steps.push(TraceStep {
+            path: path.to_owned(),
+            segment: segment.to_string(),
+            outcome,
+        });
+    }
+
+    // Applies the segment at `si` to `value`, found at `path`, and the next ones to what
+    // it selects, in the order of `Iter`
+    fn walk(&mut self, si: usize, value: &'a Value, path: String) {
+        let segment = match self.segments.get(si) {
+            Some(segment) => segment,
+            None => return self.trace.matches.push(value),
+        };
+
+        match *segment {
+            Segment::Descendant => {
+                let children = children(Children::all(value), &path);
+                let paths = children.iter().map(|child| child.1.clone()).collect();
+                self.step(&path, segment, Outcome::Selected(paths));
+                self.walk(si + 1, value, path);
+                for (child, child_path) in children {
+                    self.walk(si, child, child_path);
+                }
+            }
+            Segment::Filter(ref expr) => {
+                for (child, child_path) in children(Children::all(value), &path) {
+                    let mut comparisons = vec![];
+                    let matched = self.filter(expr, child, &mut comparisons);
+                    let outcome = Outcome::Filtered {
+                        matched,
+                        comparisons,
+                    };
+                    self.step(&child_path, segment, outcome);
+                    if matched {
+                        self.walk(si + 1, child, child_path);
+                    }
+                }
+            }
+            _ => {
+                let children = children(Children::new(value, segment), &path);
+                let paths = children.iter().map(|child| child.1.clone()).collect();
+                self.step(&path, segment, Outcome::Selected(paths));
+                for (child, child_path) in children {
+                    self.walk(si + 1, child, child_path);
+                }
+            }
+        }
+    }
+
+    // Short-circuits as `filter::matches` does, so that only the comparisons it evaluates
+    // are recorded
+    fn filter(
+        &self,
+        expr: &Expr,
+        value: &'a Value,
+        comparisons: &mut Vec<ComparisonTrace>,
+    ) -> bool {
+        match *expr {
+            Expr::Or(ref exprs) => exprs
+                .iter()
+                .any(|expr| self.filter(expr, value, comparisons)),
+            Expr::And(ref exprs) => exprs
+                .iter()
+                .all(|expr| self.filter(expr, value, comparisons)),
+            Expr::Exists(ref path) => {
+                let left = filter::select(path, value, self.root, &self.cache);
+                let result = !left.is_empty();
+                comparisons.push(ComparisonTrace {
+                    left: left.iter().map(|&value| value.clone()).collect(),
+                    operator: "exists".to_owned(),
+                    right: vec![],
+                    result,
+                });
+                result
+            }
+            Expr::Compare(ref path, operator, ref operand) => {
+                let left = filter::select(path, value, self.root, &self.cache);
+                let result =
+                    filter::matches(expr, value, self.root, None, &self.cache).unwrap_or(false);
+                let mut right = vec![];
+                self.operand(operand, &mut right);
+                comparisons.push(ComparisonTrace {
+                    left: left.iter().map(|&value| value.clone()).collect(),
+                    operator: operator.to_string(),
+                    right,
+                    result,
+                });
+                result
+            }
+        }
+    }
+
+    fn operand(&self, operand: &Operand, values: &mut Vec<Value>) {
+        match *operand {
+            Operand::String(ref content) => values.push(Value::from(content.as_str())),
+            Operand::Number(number) => values.push(Value::from(number)),
+            Operand::Array(ref items) => {
+                for item in items {
+                    self.operand(item, values);
+                }
+            }
+            Operand::Path(ref path) => {
+                let found = filter::select(path, self.root, self.root, &self.cache);
+                values.extend(found.iter().map(|&value| value.clone()));
+            }
+            Operand::Regex(ref regex) => values.push(Value::from(regex.as_str())),
+        }
+    }
+}
+
+// The children along with their normalized paths
+fn children<'a, D: Document>(children: Children<'a, D>, path: &str) -> Vec<(&'a D, String)> {
+    children
+        .map(|(child, step)| match step {
+            Step::Root => (child, path.to_owned()),
+            step => (child, format!("{}{}", path, step)),
+        })
+        .collect()
+}
+
+impl<'a> fmt::Display for Trace<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for step in &self.steps {
+            write!(f, "{}: {} -> ", step.path, step.segment)?;
+            match step.outcome {
+                Outcome::Selected(ref paths) if paths.is_empty() => writeln!(f, "nothing")?,
+                Outcome::Selected(ref paths) => writeln!(f, "{}", paths.join(", "))?,
+                Outcome::Filtered {
+                    matched,
+                    ref comparisons,
+                } => {
+                    writeln!(f, "{}", if matched { "matched" } else { "rejected" })?;
+                    for comparison in comparisons {
+                        writeln!(f, "  {}", comparison)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ComparisonTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let left = Value::Array(self.left.clone());
+        match self.operator.as_str() {
+            "exists" => write!(f, "{} exists -> {}", left, self.result),
+            operator => {
+                let right = Value::Array(self.right.clone());
+                write!(f, "{} {} {} -> {}", left, operator, right, self.result)
+            }
+        }
+    }
+}
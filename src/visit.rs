@@ -0,0 +1,164 @@
+//! Traversal of the syntax tree of selectors, to inspect and rewrite them.
+//!
+//! `Visitor` walks a `Path` by reference and `VisitorMut` by mutable reference. Every
+//! method has a default that walks the children of its node through the matching
+//! `walk_*` function, so an implementation only overrides the nodes it cares about and
+//! calls `walk_*` itself to keep descending. Paths of filter conditions and of operands
+//! are visited with `visit_path` too. An error returned by any method stops the walk.
+//!
+//! Scoping a selector to the data of a tenant, paths in filters included:
+//!
+//! ```
+//! use jsonpath::ast::{Path, Segment, Start};
+//! use jsonpath::visit::{walk_path_mut, VisitorMut};
+//! use jsonpath::{Result, Selector};
+//!
+//! struct Tenant(&'static str);
+//!
+//! impl VisitorMut for Tenant {
+//!     fn visit_path_mut(&mut self, path: &mut Path) -> Result<()> {
+//!         if path.start == Start::Root {
+//!             let base = vec![Segment::Child("tenants".into()), Segment::Child(self.0.into())];
+//!             path.segments.splice(0..0, base);
+//!         }
+//!         walk_path_mut(self, path)
+//!     }
+//! }
+//!
+//! let mut path = Selector::new("$.orders[?(@.total > $.limit)]").unwrap().to_path();
+//! Tenant("acme").visit_path_mut(&mut path).unwrap();
+//! assert_eq!(
+//!     path.to_selector().unwrap().to_string(),
+//!     "$.tenants.acme.orders[?(@.total > $.tenants.acme.limit)]"
+//! );
+//! ```
+use ast::{Expr, Operand, Path, Segment};
+use errors::*;
+
+/// Visits the nodes of a syntax tree by reference.
+pub trait Visitor {
+    fn visit_path(&mut self, path: &Path) -> Result<()> {
+        walk_path(self, path)
+    }
+
+    fn visit_segment(&mut self, segment: &Segment) -> Result<()> {
+        walk_segment(self, segment)
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) -> Result<()> {
+        walk_expr(self, expr)
+    }
+
+    fn visit_operand(&mut self, operand: &Operand) -> Result<()> {
+        walk_operand(self, operand)
+    }
+}
+
+/// Visits the nodes of a syntax tree by mutable reference, to rewrite them in place.
+pub trait VisitorMut {
+    fn visit_path_mut(&mut self, path: &mut Path) -> Result<()> {
+        walk_path_mut(self, path)
+    }
+
+    fn visit_segment_mut(&mut self, segment: &mut Segment) -> Result<()> {
+        walk_segment_mut(self, segment)
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) -> Result<()> {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_operand_mut(&mut self, operand: &mut Operand) -> Result<()> {
+        walk_operand_mut(self, operand)
+    }
+}
+
+pub fn walk_path<V: Visitor + ?Sized>(visitor: &mut V, path: &Path) -> Result<()> {
+    for segment in &path.segments {
+        visitor.visit_segment(segment)?;
+    }
+    Ok(())
+}
+
+pub fn walk_segment<V: Visitor + ?Sized>(visitor: &mut V, segment: &Segment) -> Result<()> {
+    match *segment {
+        Segment::Filter(ref condition) => visitor.visit_expr(condition),
+        _ => Ok(()),
+    }
+}
+
+pub fn walk_expr<V: Visitor + ?Sized>(visitor: &mut V, expr: &Expr) -> Result<()> {
+    match *expr {
+        Expr::Compare(ref path, _, ref operand) => {
+            visitor.visit_path(path)?;
+            visitor.visit_operand(operand)
+        }
+        Expr::And(ref conditions) | Expr::Or(ref conditions) => {
+            for condition in conditions {
+                visitor.visit_expr(condition)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn walk_operand<V: Visitor + ?Sized>(visitor: &mut V, operand: &Operand) -> Result<()> {
+    match *operand {
+        Operand::Array(ref items) => {
+            for item in items {
+                visitor.visit_operand(item)?;
+            }
+            Ok(())
+        }
+        Operand::Path(ref path) => visitor.visit_path(path),
+        _ => Ok(()),
+    }
+}
+
+pub fn walk_path_mut<V: VisitorMut + ?Sized>(visitor: &mut V, path: &mut Path) -> Result<()> {
+    for segment in &mut path.segments {
+        visitor.visit_segment_mut(segment)?;
+    }
+    Ok(())
+}
+
+pub fn walk_segment_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    segment: &mut Segment,
+) -> Result<()> {
+    match *segment {
+        Segment::Filter(ref mut condition) => visitor.visit_expr_mut(condition),
+        _ => Ok(()),
+    }
+}
+
+pub fn walk_expr_mut<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &mut Expr) -> Result<()> {
+    match *expr {
+        Expr::Compare(ref mut path, _, ref mut operand) => {
+            visitor.visit_path_mut(path)?;
+            visitor.visit_operand_mut(operand)
+        }
+        Expr::And(ref mut conditions) | Expr::Or(ref mut conditions) => {
+            for condition in conditions {
+                visitor.visit_expr_mut(condition)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+pub fn walk_operand_mut<V: VisitorMut + ?Sized>(
+    visitor: &mut V,
+    operand: &mut Operand,
+) -> Result<()> {
+    match *operand {
+        Operand::Array(ref mut items) => {
+            for item in items {
+                visitor.visit_operand_mut(item)?;
+            }
+            Ok(())
+        }
+        Operand::Path(ref mut path) => visitor.visit_path_mut(path),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,279 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::ast::{Expr, Operand, Operator, Path, Segment, Start};
+use jsonpath::{ErrorKind, Selector};
+use proptest::prelude::*;
+use serde_json::Value;
+
+fn store() -> Value {
+    json!({
+        "store": [
+            {"name": "a"},
+            {"name": "b"},
+            [
+                {"title": "cheap", "price": 8.95},
+                {"title": "dear", "price": 12.99},
+                {"title": "it's", "price": 9.5}
+            ]
+        ],
+        "limit": 10
+    })
+}
+
+#[test]
+fn test_builder() {
+    let path = Path::root()
+        .child("store")
+        .index(2)
+        .filter(Expr::path_rel(["price"]).lt(10))
+        .child("title");
+    assert_eq!(path.to_string(), "$.store[2][?(@.price < 10)].title");
+
+    let selector = path.to_selector().unwrap();
+    assert_eq!(
+        selector,
+        Selector::new("$.store[2][?(@.price<10)].title").unwrap()
+    );
+    let value = store();
+    let titles: Vec<&Value> = selector.find(&value).collect();
+    assert_eq!(titles, vec!["cheap", "it's"]);
+}
+
+#[test]
+fn test_builder_conditions() {
+    let value = store();
+    let titles = |condition: Expr| -> Vec<Value> {
+        let selector = Path::root()
+            .child("store")
+            .descendants()
+            .filter(condition)
+            .child("title")
+            .to_selector()
+            .unwrap();
+        selector.find(&value).cloned().collect()
+    };
+
+    let cheaper = Expr::path_rel(["price"]).lt(Path::root().child("limit"));
+    assert_eq!(titles(cheaper.clone()), vec!["cheap", "it's"]);
+
+    let listed = Expr::path_rel(["title"]).eq(vec!["dear", "it's"]);
+    assert_eq!(titles(listed), vec!["dear", "it's"]);
+
+    let quoted = Expr::path_rel(["title"]).eq("it's");
+    assert_eq!(titles(quoted.clone()), vec!["it's"]);
+
+    let either = Expr::path_rel(["title"]).matches("^d").or(quoted);
+    assert_eq!(titles(either.clone()), vec!["dear", "it's"]);
+
+    assert_eq!(titles(either.and(cheaper)), vec!["it's"]);
+}
+
+#[test]
+fn test_escaping() {
+    let value = json!({"a b": {"it's": 1, "back\\slash": 2, "": 3, "x/y": 4}});
+    for &(name, expected) in &[("it's", 1), ("back\\slash", 2), ("", 3), ("x/y", 4)] {
+        let selector = Path::root().child("a b").child(name).to_selector().unwrap();
+        let found: Vec<&Value> = selector.find(&value).collect();
+        assert_eq!(found, vec![&json!(expected)]);
+        assert_eq!(Selector::new(&selector.to_string()).unwrap(), selector);
+    }
+    let path = Path::root().child("a b").child("it's").child("x/y");
+    assert_eq!(path.to_string(), r"$['a b']['it\'s'].x/y");
+
+    let regex = Path::root().filter(Expr::path_rel(["s"]).matches("a/b"));
+    assert_eq!(regex.to_string(), r"$[?(@.s =~ /a\/b/)]");
+    let selector = regex.to_selector().unwrap();
+    let value = json!([{"s": "xa/b"}, {"s": "ab"}]);
+    assert_eq!(selector.find(&value).count(), 1);
+}
+
+#[test]
+fn test_to_path() {
+    let selector =
+        Selector::new("$..books[?(@.price < $.limit) || ?(@.tags == ['x'])][0:2]").unwrap();
+    let path = selector.to_path();
+    assert_eq!(path.start, Start::Root);
+    assert_eq!(
+        path.segments,
+        vec![
+            Segment::Descendant,
+            Segment::Child("books".to_owned()),
+            Segment::Filter(Expr::Or(vec![
+                Expr::Compare(
+                    Path::current().child("price"),
+                    Operator::Lower,
+                    Operand::Path(Path::root().child("limit")),
+                ),
+                Expr::Compare(
+                    Path::current().child("tags"),
+                    Operator::Equal,
+                    Operand::Array(vec![Operand::String("x".to_owned())]),
+                ),
+            ])),
+            Segment::Slice(0, 2),
+        ]
+    );
+    assert_eq!(path.to_selector().unwrap(), selector);
+    assert_eq!("$..books".parse::<Path>().unwrap().segments.len(), 2);
+
+    let keys = Path::root().integer(-1).integer(2);
+    assert_eq!(keys.segments, vec![Segment::Integer(-1), Segment::Index(2)]);
+    assert_eq!(keys.to_string(), "$[-1][2]");
+    assert_eq!(keys.to_selector().unwrap().to_path(), keys);
+}
+
+#[test]
+fn test_invalid_paths() {
+    let current = Expr::path_rel(["a"]);
+    // 2^40 alternatives once distributed
+    let either = || Expr::path_rel(["a"]).eq(1).or(Expr::path_rel(["b"]).eq(2));
+    let expanding = (1..40).fold(either(), |condition, _| condition.and(either()));
+    for &(ref path, message) in &[
+        (Path::current(), "a selector must start with `$`"),
+        (
+            Path::root().child("a").descendants(),
+            "`..` must be followed by another segment",
+        ),
+        (
+            Path::root().descendants().descendants().child("a"),
+            "`..` must be followed by another segment",
+        ),
+        (
+            Path::root().filter(Expr::Or(vec![])),
+            "a filter needs at least one comparison",
+        ),
+        (
+            Path::root().filter(current.clone().gt(-1)),
+            "numbers must be finite and not negative, found -1",
+        ),
+        (
+            Path::root().filter(current.clone().gt(f64::NAN)),
+            "numbers must be finite and not negative, found NaN",
+        ),
+        (
+            Path::root().filter(current.clone().eq(Operand::Array(vec![
+                Operand::Number(1.0),
+                Operand::String("a".to_owned()),
+            ]))),
+            "array items must be all numbers or all strings",
+        ),
+        (
+            Path::root().filter(current.clone().eq(Path::current().child("b"))),
+            "a path compared against must start with `$`",
+        ),
+        (
+            Path::root().filter(current.clone().matches("(")),
+            "regex parse error:\n    (\n    ^\nerror: unclosed group",
+        ),
+        (
+            Path::root().filter(current.eq(Operand::Regex("x".to_owned()))),
+            "regular expressions can only be used with `=~`",
+        ),
+        (
+            Path::root().segment(Segment::Integer(1)),
+            "integer keys must be negative, found 1",
+        ),
+        (
+            Path::root().filter(expanding),
+            "a filter can have at most 1024 alternatives once `&&` is distributed over `||`",
+        ),
+    ] {
+        match path.to_selector() {
+            Err(err) => match *err.kind() {
+                ErrorKind::Compile(ref msg) => assert_eq!(msg, message),
+                ref kind => panic!("unexpected error {:?} for {}", kind, path),
+            },
+            Ok(selector) => panic!("{} compiled to {}", path, selector),
+        }
+    }
+}
+
+fn name() -> impl Strategy<Value = String> {
+    prop_oneof!["[a-z]{1,3}", ".{0,4}", "[' \\\\./\\[\\]]{0,4}"]
+}
+
+fn segment() -> impl Strategy<Value = Segment> {
+    prop_oneof![
+        name().prop_map(Segment::Child),
+        Just(Segment::Wildcard),
+        (0usize..4).prop_map(Segment::Index),
+        (-4i128..0).prop_map(Segment::Integer),
+        (0usize..4, 0usize..4).prop_map(|(start, last)| Segment::Slice(start, last)),
+        (0usize..4).prop_map(Segment::SliceTo),
+        (0usize..4).prop_map(Segment::SliceFrom),
+    ]
+}
+
+fn path() -> impl Strategy<Value = Path> {
+    let step = (any::<bool>(), segment()).prop_map(|(descendant, segment)| match descendant {
+        true => vec![Segment::Descendant, segment],
+        false => vec![segment],
+    });
+    prop::collection::vec(step, 0..3).prop_map(|steps| Path {
+        start: Start::Root,
+        segments: steps.concat(),
+    })
+}
+
+fn operand() -> impl Strategy<Value = Operand> {
+    prop_oneof![
+        name().prop_map(Operand::String),
+        (0u32..100).prop_map(Operand::from),
+        (0u32..100).prop_map(|n| Operand::from(f64::from(n) / 4.0)),
+        prop::collection::vec(name(), 1..3).prop_map(Operand::from),
+        path().prop_map(Operand::Path),
+    ]
+}
+
+fn comparison() -> impl Strategy<Value = Expr> {
+    let operator = prop::sample::select(vec![
+        Operator::Equal,
+        Operator::Different,
+        Operator::Greater,
+        Operator::GreaterOrEqual,
+        Operator::Lower,
+        Operator::LowerOrEqual,
+    ]);
+    (path(), operator, operand()).prop_map(|(mut path, operator, operand)| {
+        path.start = Start::Current;
+        Expr::Compare(path, operator, operand)
+    })
+}
+
+fn condition() -> impl Strategy<Value = Expr> {
+    (
+        comparison(),
+        prop::option::of((any::<bool>(), comparison())),
+    )
+        .prop_map(|(first, rest)| match rest {
+            Some((true, second)) => first.and(second),
+            Some((false, second)) => first.or(second),
+            None => first,
+        })
+}
+
+fn expression() -> impl Strategy<Value = Path> {
+    (path(), prop::option::of(condition()), path()).prop_map(|(path, condition, rest)| {
+        let path = match condition {
+            Some(condition) => path.filter(condition),
+            None => path,
+        };
+        Path {
+            segments: [path.segments, rest.segments].concat(),
+            ..path
+        }
+    })
+}
+
+proptest! {
+    #[test]
+    fn built_paths_parse_back(path in expression()) {
+        let selector = path.to_selector().unwrap();
+        let parsed: Selector = selector.to_string().parse().unwrap();
+        prop_assert_eq!(&parsed, &selector);
+        prop_assert_eq!(parsed.to_path(), path);
+    }
+}
@@ -0,0 +1,152 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::Selector;
+use proptest::prelude::*;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+fn hash(selector: &Selector) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    selector.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_display_and_debug() {
+    let selector = Selector::new("$..books[?(@.price<=8.950)]..[0]").unwrap();
+    assert_eq!(selector.to_string(), "$..books[?(@.price <= 8.95)]..[0]");
+    assert_eq!(
+        format!("{:?}", selector),
+        "Selector(\"$..books[?(@.price <= 8.95)]..[0]\")"
+    );
+}
+
+#[test]
+fn test_from_str() {
+    let selector: Selector = "$.a.b".parse().unwrap();
+    assert_eq!(selector, Selector::new("$.a.b").unwrap());
+    assert!("$.a[".parse::<Selector>().is_err());
+}
+
+// Compiled regular expressions have interior mutability, but selectors hash by their
+// canonical expression only
+#[allow(clippy::mutable_key_type)]
+#[test]
+fn test_equality_and_hash() {
+    let a = Selector::new("$.a[?(@.b==1)]").unwrap();
+    let b = Selector::new("$.a[?(@.b == 1.0)]").unwrap();
+    let c = Selector::new("$.a[?(@.b == 2)]").unwrap();
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+    assert_ne!(a, c);
+
+    let cache: HashSet<Selector> = vec![a, b.clone(), c].into_iter().collect();
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains(&b));
+}
+
+#[test]
+fn test_clone() {
+    let value = json!({"a": [{"b": 1}, {"b": 2}]});
+    let selector = Selector::new("$.a[?(@.b > 1)].b").unwrap();
+    let cloned = selector.clone();
+    assert_eq!(cloned, selector);
+    assert_eq!(
+        cloned.find(&value).collect::<Vec<_>>(),
+        selector.find(&value).collect::<Vec<_>>()
+    );
+}
+
+fn path() -> impl Strategy<Value = String> {
+    let segment = prop::sample::select(vec![
+        ".a", ".b_c", ".*", "..a", "..*", "..[0]", "[1]", "[0:1]", "[:2]", "[1:]", "[h'0A']",
+    ]);
+    prop::collection::vec(segment, 0..3).prop_map(|segments| segments.concat())
+}
+
+fn condition() -> impl Strategy<Value = String> {
+    let operator = prop::sample::select(vec!["==", "!=", ">", ">=", "<", "<="]);
+    let operand = prop_oneof![
+        (0u32..1000).prop_map(|n| n.to_string()),
+        (0u32..1000, 0u32..100).prop_map(|(n, f)| format!("{}.{:02}", n, f)),
+        "[a-z ]{1,5}".prop_map(|s| format!("'{}'", s)),
+        Just("[1,2, 3]".to_owned()),
+        Just("[0.5,2.0]".to_owned()),
+        Just("['x',  'y z']".to_owned()),
+        path().prop_map(|path| format!("$.a{}", path)),
+    ];
+    let comparison = (
+        prop::sample::select(vec!["@", "$"]),
+        path(),
+        operator,
+        operand,
+    )
+        .prop_map(|(start, path, operator, operand)| {
+            format!("{}{} {} {}", start, path, operator, operand)
+        });
+    let regex = (
+        path(),
+        prop::sample::select(vec!["/^a+/", "/b\\/c/i", "/x y/"]),
+    )
+        .prop_map(|(path, regex)| format!("@{}=~{}", path, regex));
+    prop_oneof![comparison, regex]
+}
+
+fn filter() -> impl Strategy<Value = String> {
+    (
+        condition(),
+        prop::collection::vec(
+            (prop::sample::select(vec!["&&", " || "]), condition()),
+            0..2,
+        ),
+    )
+        .prop_map(|(first, rest)| {
+            let mut filter = format!("[?({})", first);
+            for (joiner, condition) in rest {
+                filter.push_str(&format!("{}?({})", joiner, condition));
+            }
+            filter + "]"
+        })
+}
+
+fn expression() -> impl Strategy<Value = String> {
+    let segment = prop_oneof![path(), filter()];
+    prop::collection::vec(segment, 0..4).prop_map(|segments| format!("${}", segments.concat()))
+}
+
+fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        (0..20).prop_map(Value::from),
+        "[a-z]{1,2}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+            prop::collection::btree_map("a|b_c", inner, 0..3)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn canonical_expressions_round_trip(expression in expression(), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let canonical = selector.to_string();
+        let parsed: Selector = canonical.parse().unwrap();
+
+        prop_assert_eq!(parsed.to_string(), canonical);
+        prop_assert_eq!(&parsed, &selector);
+        prop_assert_eq!(hash(&parsed), hash(&selector));
+
+        let expected: Vec<*const Value> = selector.find(&value).map(|v| v as *const Value).collect();
+        let found: Vec<*const Value> = parsed.find(&value).map(|v| v as *const Value).collect();
+        prop_assert_eq!(found, expected);
+    }
+}
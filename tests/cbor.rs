@@ -0,0 +1,108 @@
+#![cfg(feature = "ciborium")]
+
+extern crate ciborium;
+extern crate jsonpath;
+
+use ciborium::value::Value;
+use jsonpath::{Document, ErrorKind, Kind, Selector};
+
+fn text(text: &str) -> Value {
+    Value::Text(text.to_owned())
+}
+
+fn reading(temperature: f64, humidity: i64) -> Value {
+    Value::Map(vec![
+        (text("t"), Value::Float(temperature)),
+        (text("h"), Value::Integer(humidity.into())),
+    ])
+}
+
+// Telemetry keyed by integers, as in CBOR based protocols
+fn telemetry() -> Value {
+    let value = Value::Map(vec![
+        (Value::Integer(1.into()), text("sensor-7")),
+        (
+            Value::Integer(2.into()),
+            Value::Array(vec![reading(21.5, 40), reading(23.0, 38)]),
+        ),
+        (Value::Bytes(vec![0xca, 0xfe]), text("signature")),
+        (
+            Value::Integer((-1).into()),
+            Value::Tag(1, Box::new(1_700_000_000.into())),
+        ),
+    ]);
+
+    // Through the wire and back
+    let mut bytes = vec![];
+    ciborium::ser::into_writer(&value, &mut bytes).unwrap();
+    ciborium::de::from_reader(&bytes[..]).unwrap()
+}
+
+fn find<'a>(expression: &str, root: &'a Value) -> Vec<&'a Value> {
+    Selector::new(expression).unwrap().find(root).collect()
+}
+
+#[test]
+fn test_integer_keys() {
+    let root = telemetry();
+    assert_eq!(find("$[1]", &root), vec![&text("sensor-7")]);
+    assert_eq!(find("$[2][1].h", &root), vec![&Value::Integer(38.into())]);
+    assert_eq!(
+        find("$[2][?(@.t > 22)].h", &root),
+        vec![&Value::Integer(38.into())]
+    );
+    assert!(find("$[3]", &root).is_empty());
+    assert!(find("$[-2]", &root).is_empty());
+}
+
+#[test]
+fn test_byte_string_keys() {
+    let root = telemetry();
+    assert_eq!(find("$[h'cafe']", &root), vec![&text("signature")]);
+    assert_eq!(find("$[h'CAFE']", &root), vec![&text("signature")]);
+    assert!(find("$[h'']", &root).is_empty());
+}
+
+#[test]
+fn test_tags_and_kinds() {
+    let root = telemetry();
+    let all = find("$.*", &root);
+    assert_eq!(all.len(), 4);
+    assert_eq!(all[3].kind(), Kind::Number);
+    assert_eq!(all[3].as_f64(), Some(1_700_000_000.0));
+
+    let bytes = Value::Array(vec![Value::Bytes(vec![1, 2])]);
+    assert_eq!(find("$[0]", &bytes)[0].kind(), Kind::Bytes);
+}
+
+#[test]
+fn test_normalized_paths() {
+    let root = telemetry();
+    let selector = Selector::new("$.*.missing").unwrap();
+    let paths: Vec<String> = selector
+        .find_strict(&root)
+        .map(|found| match *found.unwrap_err().kind() {
+            ErrorKind::TypeMismatch(ref path, _, _) => path.clone(),
+            ref kind => panic!("unexpected error: {:?}", kind),
+        })
+        .collect();
+    assert_eq!(paths, vec!["$[1]", "$[2]", "$[h'cafe']", "$[-1]"]);
+
+    // Every reported path selects the value it was reported for
+    for path in &paths {
+        let found = find(path, &root);
+        assert_eq!(found.len(), 1, "{}", path);
+    }
+    assert_eq!(
+        find("$[-1]", &root),
+        vec![&Value::Tag(1, Box::new(1_700_000_000.into()))]
+    );
+
+    let selector = Selector::new("$[h'beef']").unwrap();
+    let errors: Vec<String> = selector
+        .find_strict(&root)
+        .map(|found| found.unwrap_err().to_string())
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].contains("[h'beef']"), "{}", errors[0]);
+}
@@ -0,0 +1,45 @@
+// Fixtures shared by the integration tests, each of which uses only some of them
+#![allow(dead_code)]
+
+use proptest::prelude::*;
+use serde_json::{self, Value};
+use std::fs::File;
+use std::io::Read;
+
+pub fn read_data() -> Value {
+    let mut data = String::new();
+    let mut data_file = File::open("tests/data.json").unwrap();
+    let _ = data_file.read_to_string(&mut data).unwrap();
+    serde_json::from_str(&data).unwrap()
+}
+
+// Selectors made of fewer than `max_len` of `segments`
+pub fn expression(
+    segments: &'static [&'static str],
+    max_len: usize,
+) -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(segments), 0..max_len)
+        .prop_map(|segments| format!("${}", segments.concat()))
+}
+
+// Small documents whose members are named `a` and `b`
+pub fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![Just(Value::Null), any::<i32>().prop_map(Value::from)];
+    leaf.prop_recursive(4, 32, 4, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..4).prop_map(Value::from),
+            prop::collection::btree_map("[ab]", inner, 0..3)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+// Pointers to the values of a document, in document order
+pub fn document_order(value: &Value, order: &mut Vec<*const Value>) {
+    order.push(value);
+    match *value {
+        Value::Array(ref items) => items.iter().for_each(|item| document_order(item, order)),
+        Value::Object(ref map) => map.values().for_each(|item| document_order(item, order)),
+        _ => {}
+    }
+}
@@ -0,0 +1,199 @@
+extern crate jsonpath;
+
+use jsonpath::{Document, ErrorKind, Key, Kind, Selector, SelectorSet};
+use std::borrow::Cow;
+use std::slice;
+
+// A tree keeping the members of objects in insertion order
+#[derive(Debug, PartialEq)]
+enum Node {
+    Null,
+    Number(f64),
+    Text(String),
+    List(Vec<Node>),
+    Record(Vec<(String, Node)>),
+}
+
+struct Members<'a> {
+    iter: slice::Iter<'a, (String, Node)>,
+}
+
+impl<'a> Iterator for Members<'a> {
+    type Item = (Key<'a>, &'a Node);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(name, node)| (Key::Name(name), node))
+    }
+}
+
+impl Document for Node {
+    type Members<'a> = Members<'a>;
+    type Elements<'a> = slice::Iter<'a, Node>;
+
+    fn kind(&self) -> Kind {
+        match *self {
+            Node::Null => Kind::Null,
+            Node::Number(_) => Kind::Number,
+            Node::Text(_) => Kind::String,
+            Node::List(_) => Kind::Array,
+            Node::Record(_) => Kind::Object,
+        }
+    }
+
+    fn member<'a>(&'a self, name: &str) -> Option<(&'a str, &'a Self)> {
+        match *self {
+            Node::Record(ref members) => members
+                .iter()
+                .find(|member| member.0 == name)
+                .map(|(name, node)| (name.as_str(), node)),
+            _ => None,
+        }
+    }
+
+    fn members(&self) -> Option<Members<'_>> {
+        match *self {
+            Node::Record(ref members) => Some(Members {
+                iter: members.iter(),
+            }),
+            _ => None,
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<&Self> {
+        match *self {
+            Node::List(ref nodes) => nodes.get(index),
+            _ => None,
+        }
+    }
+
+    fn elements(&self) -> Option<slice::Iter<'_, Node>> {
+        match *self {
+            Node::List(ref nodes) => Some(nodes.iter()),
+            _ => None,
+        }
+    }
+
+    fn array_len(&self) -> Option<usize> {
+        match *self {
+            Node::List(ref nodes) => Some(nodes.len()),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<Cow<'_, str>> {
+        match *self {
+            Node::Text(ref text) => Some(Cow::Borrowed(text)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Node::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        None
+    }
+}
+
+fn text(text: &str) -> Node {
+    Node::Text(text.to_owned())
+}
+
+fn book(title: &str, price: f64) -> Node {
+    Node::Record(vec![
+        ("title".to_owned(), text(title)),
+        ("price".to_owned(), Node::Number(price)),
+    ])
+}
+
+fn store() -> Node {
+    Node::Record(vec![
+        ("name".to_owned(), text("Corner")),
+        (
+            "books".to_owned(),
+            Node::List(vec![
+                book("Sayings of the Century", 8.95),
+                book("Sword of Honour", 12.99),
+                book("Moby Dick", 8.99),
+            ]),
+        ),
+        ("limit".to_owned(), Node::Number(10.0)),
+        ("owner".to_owned(), Node::Null),
+    ])
+}
+
+fn titles(expression: &str, root: &Node) -> Vec<String> {
+    Selector::new(expression)
+        .unwrap()
+        .find(root)
+        .map(|node| node.as_str().unwrap().into_owned())
+        .collect()
+}
+
+#[test]
+fn test_find() {
+    let root = store();
+    assert_eq!(titles("$.books[1].title", &root), vec!["Sword of Honour"]);
+    assert_eq!(
+        titles("$.books[1:].title", &root),
+        vec!["Sword of Honour", "Moby Dick"]
+    );
+    assert_eq!(
+        titles("$.books[?(@.price < $.limit)].title", &root),
+        vec!["Sayings of the Century", "Moby Dick"]
+    );
+    assert_eq!(
+        titles("$.books[?(@.title =~ /^s/i)].title", &root),
+        vec!["Sayings of the Century", "Sword of Honour"]
+    );
+}
+
+#[test]
+fn test_members_in_document_order() {
+    let root = store();
+    let found: Vec<&Node> = Selector::new("$.*").unwrap().find(&root).collect();
+    assert_eq!(found.len(), 4);
+    assert_eq!(found[0], &text("Corner"));
+    assert_eq!(found[3], &Node::Null);
+
+    assert_eq!(
+        titles("$..title", &root),
+        vec!["Sayings of the Century", "Sword of Honour", "Moby Dick"]
+    );
+}
+
+#[test]
+fn test_find_strict() {
+    let root = store();
+    let selector = Selector::new("$.books.title").unwrap();
+    let errors: Vec<_> = selector
+        .find_strict(&root)
+        .map(|found| found.unwrap_err())
+        .collect();
+    match *errors[0].kind() {
+        ErrorKind::TypeMismatch(ref path, ref expected, ref found) => {
+            assert_eq!(path, "$['books']");
+            assert_eq!(expected, "object");
+            assert_eq!(found, "array");
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_selector_set() {
+    let root = store();
+    let set = SelectorSet::new(["$.name", "$.books[0].title", "$.address"]).unwrap();
+    let found = set.find(&root);
+    assert_eq!(found[0], vec![&text("Corner")]);
+    assert_eq!(found[1], vec![&text("Sayings of the Century")]);
+    assert!(found[2].is_empty());
+    assert_eq!(
+        set.matches(&root).iter().collect::<Vec<usize>>(),
+        vec![0, 1]
+    );
+}
@@ -0,0 +1,113 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{expression, json};
+use jsonpath::{IndexedDocument, Selector};
+use proptest::prelude::*;
+use serde_json::Value;
+use std::fs::File;
+
+fn paths(index: &IndexedDocument, found: Vec<&Value>) -> Vec<String> {
+    found.into_iter().map(|v| index.path(v).unwrap()).collect()
+}
+
+#[test]
+fn test_find_in_index() {
+    let root: Value = serde_json::from_reader(File::open("tests/data.json").unwrap()).unwrap();
+    let index = IndexedDocument::new(&root);
+
+    for expression in &[
+        "$..price",
+        "$..author",
+        "$.store..price",
+        "$..books[?(@.price > 10)].title",
+        "$..books..category",
+        "$..missing",
+        "$.*",
+    ] {
+        let selector = Selector::new(expression).unwrap();
+        let expected: Vec<&Value> = selector.find(&root).collect();
+        let found: Vec<&Value> = selector.find(&index).collect();
+        assert_eq!(found, expected, "{}", expression);
+    }
+}
+
+#[test]
+fn test_descendants_follow_find_order() {
+    let root = json!({"a": {"b": 1}, "b": 2});
+    let index = IndexedDocument::new(&root);
+
+    // `b` of the root comes before the `b` of `a`, as with `find`
+    let selector = Selector::new("$..b").unwrap();
+    let found = selector.find(&index).collect();
+    assert_eq!(paths(&index, found), vec!["$['b']", "$['a']['b']"]);
+
+    let selector = Selector::new("$.a..b").unwrap();
+    let found = selector.find(&index).collect();
+    assert_eq!(paths(&index, found), vec!["$['a']['b']"]);
+}
+
+#[test]
+fn test_parents_and_ancestors() {
+    let root = json!({"orders": [{"lines": [{"sku": "x"}]}]});
+    let index = IndexedDocument::new(&root);
+    assert_eq!(index.node_count(), 6);
+
+    let sku = &root["orders"][0]["lines"][0]["sku"];
+    assert_eq!(index.parent(sku), Some(&root["orders"][0]["lines"][0]));
+    assert_eq!(
+        index.ancestors(sku).collect::<Vec<_>>(),
+        vec![
+            &root["orders"][0]["lines"][0],
+            &root["orders"][0]["lines"],
+            &root["orders"][0],
+            &root["orders"],
+            &root,
+        ]
+    );
+    assert_eq!(
+        index.path(sku).unwrap(),
+        "$['orders'][0]['lines'][0]['sku']"
+    );
+
+    assert_eq!(index.parent(&root), None);
+    assert_eq!(index.ancestors(&root).count(), 0);
+    assert_eq!(index.path(&root).unwrap(), "$");
+
+    // Values from elsewhere are not part of the document
+    let other = json!("x");
+    assert_eq!(index.parent(&other), None);
+    assert_eq!(index.path(&other), None);
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a",
+    ".*",
+    "[0]",
+    "[1]",
+    "[0:1]",
+    "..a",
+    "..b",
+    "..*",
+    "..[0]",
+    "[?(@.a > 0)]",
+];
+
+proptest! {
+    #[test]
+    fn index_finds_the_same_values(expression in expression(SEGMENTS, 6), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let index = IndexedDocument::new(&value);
+
+        let expected: Vec<&Value> = selector.find(&value).collect();
+        let found: Vec<&Value> = selector.find(&index).collect();
+        prop_assert_eq!(found.len(), expected.len());
+        for (found, expected) in found.iter().zip(&expected) {
+            prop_assert!(::std::ptr::eq(*found, *expected));
+        }
+    }
+}
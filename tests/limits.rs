@@ -0,0 +1,169 @@
+extern crate jsonpath;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::{Error, ErrorKind, EvalLimits, Selector};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+
+fn find_limited<'a>(
+    expression: &str,
+    value: &'a Value,
+    limits: &EvalLimits,
+) -> (Vec<&'a Value>, Option<Error>) {
+    let selector = Selector::new(expression).unwrap();
+    let mut found = vec![];
+    for result in selector.find_limited(value, limits) {
+        match result {
+            Ok(value) => found.push(value),
+            Err(e) => return (found, Some(e)),
+        }
+    }
+    (found, None)
+}
+
+fn limit_exceeded(error: Option<Error>) -> String {
+    match error.as_ref().map(Error::kind) {
+        Some(ErrorKind::LimitExceeded(limit)) => limit.clone(),
+        kind => panic!("expected a limit to be exceeded, got {:?}", kind),
+    }
+}
+
+fn items(count: usize) -> Value {
+    let items: Vec<Value> = (0..count).map(|i| json!({ "value": i })).collect();
+    json!({ "items": items })
+}
+
+#[test]
+fn test_without_limits() {
+    let value = items(10);
+    let (found, error) = find_limited("$..value", &value, &EvalLimits::default());
+    assert_eq!(found.len(), 10);
+    assert!(error.is_none());
+}
+
+#[test]
+fn test_max_results() {
+    let value = items(10);
+    let limits = EvalLimits {
+        max_results: Some(4),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$.items.*.value", &value, &limits);
+    assert_eq!(found, vec![0, 1, 2, 3]);
+    assert_eq!(limit_exceeded(error), "more than 4 results");
+
+    // Reaching the limit is fine
+    let (found, error) = find_limited("$.items[0:3].value", &value, &limits);
+    assert_eq!(found.len(), 4);
+    assert!(error.is_none());
+}
+
+#[test]
+fn test_max_nodes() {
+    let value = items(10);
+    let limits = EvalLimits {
+        max_nodes: Some(8),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$.items.*.value", &value, &limits);
+    assert!(found.len() < 10);
+    assert_eq!(limit_exceeded(error), "more than 8 nodes visited");
+
+    let (found, error) = find_limited("$.items[2].value", &value, &limits);
+    assert_eq!(found, vec![2]);
+    assert!(error.is_none());
+}
+
+#[test]
+fn test_max_nodes_counts_filters() {
+    // Every item compares with an absolute path, which visits the whole array once
+    let value = items(100);
+    let expression = "$.items[?(@.value >= $.items[?(@.value == 95)].value)].value";
+    let limits = EvalLimits {
+        max_nodes: Some(150),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited(expression, &value, &limits);
+    assert!(found.is_empty());
+    assert_eq!(limit_exceeded(error), "more than 150 nodes visited");
+
+    let limits = EvalLimits {
+        max_nodes: Some(1_000),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited(expression, &value, &limits);
+    assert_eq!(found, vec![95, 96, 97, 98, 99]);
+    assert!(error.is_none());
+}
+
+#[test]
+fn test_max_filter_depth() {
+    let value = items(3);
+    let limits = EvalLimits {
+        max_filter_depth: Some(1),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$.items[?(@.value > 0)].value", &value, &limits);
+    assert_eq!(found, vec![1, 2]);
+    assert!(error.is_none());
+
+    // Rejected before anything is evaluated
+    let expression = "$.items[?(@.value == $.items[?(@.value == 1)].value)].value";
+    let (found, error) = find_limited(expression, &value, &limits);
+    assert!(found.is_empty());
+    assert_eq!(limit_exceeded(error), "filters nested more than 1 deep");
+}
+
+#[test]
+fn test_max_depth() {
+    let mut value = json!({"x": 0});
+    for i in 1..20 {
+        value = json!({"x": i, "next": value});
+    }
+    let limits = EvalLimits {
+        max_depth: Some(10),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$.next.next.x", &value, &limits);
+    assert_eq!(found, vec![17]);
+    assert!(error.is_none());
+
+    let (found, error) = find_limited("$..x", &value, &limits);
+    assert!(!found.is_empty() && found.len() < 20);
+    assert_eq!(limit_exceeded(error), "more than 10 levels deep");
+}
+
+#[test]
+fn test_deadline() {
+    let value = items(10);
+    let limits = EvalLimits {
+        deadline: Some(Instant::now()),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$..value", &value, &limits);
+    assert!(found.is_empty());
+    assert_eq!(limit_exceeded(error), "deadline passed");
+
+    let limits = EvalLimits {
+        deadline: Some(Instant::now() + Duration::from_secs(60)),
+        ..EvalLimits::default()
+    };
+    let (found, error) = find_limited("$..value", &value, &limits);
+    assert_eq!(found.len(), 10);
+    assert!(error.is_none());
+}
+
+#[test]
+fn test_iteration_ends_after_the_error() {
+    let value = items(10);
+    let selector = Selector::new("$.items.*").unwrap();
+    let limits = EvalLimits {
+        max_results: Some(1),
+        ..EvalLimits::default()
+    };
+    let mut iter = selector.find_limited(&value, &limits);
+    assert!(iter.next().unwrap().is_ok());
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+}
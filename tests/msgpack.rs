@@ -0,0 +1,71 @@
+#![cfg(feature = "rmpv")]
+
+extern crate jsonpath;
+extern crate rmpv;
+
+use jsonpath::{Document, Kind, Selector};
+use rmpv::Value;
+
+fn decoded(value: &Value) -> Value {
+    let mut bytes = vec![];
+    rmpv::encode::write_value(&mut bytes, value).unwrap();
+    rmpv::decode::read_value(&mut &bytes[..]).unwrap()
+}
+
+fn event() -> Value {
+    decoded(&Value::Map(vec![
+        (Value::from("device"), Value::from("thermostat")),
+        (
+            Value::from(0),
+            Value::Array(vec![Value::from(19.5), Value::from(21), Value::from(22.5)]),
+        ),
+        (Value::Binary(vec![0x01]), Value::from(true)),
+        (Value::from(u64::MAX), Value::from("max")),
+        (Value::Ext(3, vec![0xff]), Value::Nil),
+    ]))
+}
+
+fn find(expression: &str, root: &Value) -> Vec<Value> {
+    Selector::new(expression)
+        .unwrap()
+        .find(root)
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn test_find() {
+    let root = event();
+    assert_eq!(find("$.device", &root), vec![Value::from("thermostat")]);
+    assert_eq!(find("$[0][1]", &root), vec![Value::from(21)]);
+    assert_eq!(
+        find("$[0][?(@ > 20)]", &root),
+        vec![Value::from(21), Value::from(22.5)]
+    );
+    assert_eq!(find("$[h'01']", &root), vec![Value::from(true)]);
+    assert_eq!(
+        find("$[18446744073709551615]", &root),
+        vec![Value::from("max")]
+    );
+    assert_eq!(find("$.*", &root).len(), 5);
+}
+
+#[test]
+fn test_kinds() {
+    // A string that is not UTF-8
+    let invalid = rmpv::decode::read_value(&mut &[0xa1, 0xff][..]).unwrap();
+    let root = Value::Array(vec![
+        Value::Binary(vec![1]),
+        Value::Ext(1, vec![2]),
+        invalid,
+        Value::from("text"),
+    ]);
+    let kinds: Vec<Kind> = find("$.*", &root)
+        .iter()
+        .map(|value| value.kind())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![Kind::Bytes, Kind::Bytes, Kind::Bytes, Kind::String]
+    );
+}
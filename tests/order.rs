@@ -0,0 +1,138 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{expression, json};
+use jsonpath::{IndexedDocument, Selector};
+use proptest::prelude::*;
+use serde_json::Value;
+use std::collections::HashSet;
+
+fn find<'a>(expression: &str, value: &'a Value) -> Vec<&'a Value> {
+    Selector::new(expression).unwrap().find(value).collect()
+}
+
+#[test]
+fn test_wildcard_over_object_follows_the_map() {
+    let value: Value = serde_json::from_str(r#"{"b": 1, "c": 2, "a": 3}"#).unwrap();
+    let members: Vec<&Value> = value.as_object().unwrap().values().collect();
+    assert_eq!(find("$.*", &value), members);
+
+    if cfg!(feature = "preserve_order") {
+        assert_eq!(find("$.*", &value), vec![1, 2, 3]);
+    } else {
+        assert_eq!(find("$.*", &value), vec![3, 1, 2]);
+    }
+}
+
+#[test]
+fn test_wildcard_over_array_follows_indexes() {
+    let value = json!([3, 1, 2]);
+    assert_eq!(find("$.*", &value), vec![3, 1, 2]);
+}
+
+#[test]
+fn test_matches_follow_the_values_they_were_found_from() {
+    let value = json!([{"a": 1, "b": 2}, {"a": 3}, {"b": 4}]);
+    assert_eq!(find("$.*.a", &value), vec![1, 3]);
+    assert_eq!(find("$[0:1].*", &value), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_filters_keep_the_order_of_candidates() {
+    let value = json!({"items": [{"n": 5}, {"n": 1}, {"n": 3}, {"n": 4}]});
+    assert_eq!(find("$.items[?(@.n > 2)].n", &value), vec![5, 3, 4]);
+}
+
+#[test]
+fn test_descendants_apply_the_next_segment_in_document_order() {
+    let value: Value = serde_json::from_str(r#"{"a": {"b": 1}, "b": 2}"#).unwrap();
+
+    // The `b` of the root first, then the ones below its children
+    assert_eq!(find("$..b", &value), vec![2, 1]);
+
+    let value = json!({"a": [[1, 2], [3]], "b": [4]});
+    assert_eq!(
+        find("$..[0]", &value),
+        vec![&json!([1, 2]), &json!(1), &json!(3), &json!(4)]
+    );
+    assert_eq!(
+        find("$..*", &value),
+        vec![
+            &json!([[1, 2], [3]]),
+            &json!([4]),
+            &json!([1, 2]),
+            &json!([3]),
+            &json!(1),
+            &json!(2),
+            &json!(3),
+            &json!(4),
+        ]
+    );
+}
+
+#[test]
+fn test_unique() {
+    let value = json!({"a": {"b": {"c": 1}}, "d": 1});
+    let selector = Selector::new("$..*..*").unwrap();
+
+    let all: Vec<&Value> = selector.find(&value).collect();
+    assert_eq!(all, vec![&json!({"c": 1}), &json!(1), &json!(1)]);
+
+    // `c` is reached from `a` and from `b`; `d` is never, though it is equal to `c`
+    let unique: Vec<&Value> = selector.find(&value).unique().collect();
+    assert_eq!(unique, vec![&json!({"c": 1}), &json!(1)]);
+
+    // Equal values at different places are all yielded
+    let value = json!([1, 1, 1]);
+    assert_eq!(find("$.*", &value).len(), 3);
+    assert_eq!(
+        Selector::new("$.*").unwrap().find(&value).unique().count(),
+        3
+    );
+}
+
+#[test]
+fn test_unique_in_index() {
+    let value = json!({"a": {"a": {"a": 1}}});
+    let index = IndexedDocument::new(&value);
+    let selector = Selector::new("$..a..a").unwrap();
+    assert_eq!(selector.find(&index).count(), 3);
+    assert_eq!(selector.find(&index).unique().count(), 2);
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a",
+    ".b",
+    ".*",
+    "[0]",
+    "[1]",
+    "[0:2]",
+    "..a",
+    "..*",
+    "..[0]",
+    "[?(@.a > 0)]",
+];
+
+proptest! {
+    #[test]
+    fn unique_keeps_the_first_path_to_every_value(expression in expression(SEGMENTS, 6), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+
+        let mut seen = HashSet::new();
+        let expected: Vec<*const Value> = selector
+            .find(&value)
+            .map(|v| v as *const Value)
+            .filter(|&v| seen.insert(v))
+            .collect();
+        let found: Vec<*const Value> = selector
+            .find(&value)
+            .unique()
+            .map(|v| v as *const Value)
+            .collect();
+        prop_assert_eq!(found, expected);
+    }
+}
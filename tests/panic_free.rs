@@ -0,0 +1,128 @@
+extern crate jsonpath;
+extern crate proptest;
+extern crate serde_json;
+
+use jsonpath::Selector;
+use proptest::prelude::*;
+use serde_json::Value;
+
+const TOKENS: &[&str] = &[
+    "$",
+    "@",
+    ".",
+    "..",
+    "*",
+    "[",
+    "]",
+    "?(",
+    "(",
+    ")",
+    ":",
+    ",",
+    " ",
+    "'",
+    "==",
+    "!=",
+    "<",
+    "<=",
+    ">",
+    ">=",
+    "=~",
+    "/",
+    "/^a.*/i",
+    "&&",
+    "||",
+    "0",
+    "1",
+    "7",
+    "18446744073709551616",
+    "1.5",
+    "'a'",
+    "'Moby Dick'",
+    "store",
+    "books",
+    "price",
+    "title",
+    "é",
+    "\n",
+];
+
+fn expression() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(TOKENS), 0..40).prop_map(|tokens| tokens.concat())
+}
+
+fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i32>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        "[a-z ]{0,8}".prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::from),
+            prop::collection::btree_map("[a-z]{1,6}", inner, 0..6)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn evaluate(selector: &Selector, value: &Value) {
+    let _ = selector.find(value).count();
+    let _ = selector.find_strict(value).count();
+    let _ = selector.stream_slice(value.to_string().as_bytes(), |_| ());
+}
+
+proptest! {
+    #[test]
+    fn parse_any_string_does_not_panic(expression in "\\PC*") {
+        let _ = Selector::new(&expression);
+    }
+
+    #[test]
+    fn parse_token_soup_does_not_panic(expression in expression()) {
+        let _ = Selector::new(&expression);
+    }
+
+    #[test]
+    fn evaluate_does_not_panic(expression in expression(), value in json()) {
+        for expression in &[expression.clone(), format!("${}", expression)] {
+            if let Ok(selector) = Selector::new(expression) {
+                evaluate(&selector, &value);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_deeply_nested_expression_is_rejected() {
+    let expression = format!("$.a{}", "[?(@.a".repeat(10_000));
+    assert!(Selector::new(&expression).is_err());
+}
+
+#[test]
+fn test_brackets_in_strings_do_not_nest() {
+    let brackets = "[(".repeat(40);
+    for expression in &[
+        format!("$[?(@.a == '{}')]", brackets),
+        format!("$['{}'].a", brackets),
+        format!(r"$[?(@.a == 'it\'s {}')]", brackets),
+        format!(r"$[?(@.a =~ /{}/)]", r"\[\(".repeat(40)),
+    ] {
+        assert!(Selector::new(expression).is_ok(), "{}", expression);
+    }
+    let nested = format!("$.a/b{}", "[?(@.a".repeat(40));
+    assert!(Selector::new(&nested).is_err());
+}
+
+#[test]
+fn test_deeply_nested_filters_evaluate() {
+    let mut expression = String::from("@.a == 1");
+    for _ in 0..15 {
+        expression = format!("@[?({})].a == 1", expression);
+    }
+    let selector = Selector::new(&format!("$[?({})]", expression)).unwrap();
+    let value: Value = serde_json::from_str(r#"[{"a": [{"a": 1}]}]"#).unwrap();
+    evaluate(&selector, &value);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "rayon")]
+
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{expression, json};
+use jsonpath::Selector;
+use proptest::prelude::*;
+use serde_json::Value;
+use std::fs::File;
+
+fn records() -> Value {
+    let records: Vec<Value> = (0..10_000)
+        .map(|i| json!({ "id": i, "score": (i % 100) as f64 / 100.0, "tags": ["a", "b"] }))
+        .collect();
+    json!({ "limit": 0.97, "records": records })
+}
+
+#[test]
+fn test_par_find_keeps_document_order() {
+    let root = records();
+    for expression in &[
+        "$.records[?(@.score > 0.9)].id",
+        "$.records[?(@.score >= $.limit)].id",
+        "$.records[100:200].tags.*",
+        "$.records.*.tags[1]",
+        "$..tags[0]",
+        "$.limit",
+    ] {
+        let selector = Selector::new(expression).unwrap();
+        let expected: Vec<&Value> = selector.find(&root).collect();
+        assert!(!expected.is_empty(), "{}", expression);
+        assert_eq!(selector.par_find(&root), expected, "{}", expression);
+    }
+}
+
+#[test]
+fn test_par_find_on_data() {
+    let root: Value = serde_json::from_reader(File::open("tests/data.json").unwrap()).unwrap();
+    let selector = Selector::new("$.store.books[?(@.price < 10)].title").unwrap();
+    assert_eq!(
+        selector.par_find(&root),
+        selector.find(&root).collect::<Vec<&Value>>()
+    );
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a",
+    ".b",
+    ".*",
+    "[0]",
+    "[1]",
+    "[0:1]",
+    "[1:]",
+    "..a",
+    "..*",
+    "[?(@.a > 0)]",
+    "[?(@ < 0)]",
+];
+
+proptest! {
+    #[test]
+    fn par_find_finds_what_find_finds(expression in expression(SEGMENTS, 6), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let expected: Vec<&Value> = selector.find(&value).collect();
+        prop_assert_eq!(selector.par_find(&value), expected);
+    }
+}
@@ -0,0 +1,152 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{document_order, expression, json};
+use jsonpath::{ErrorKind, Selector};
+use proptest::prelude::*;
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::fs;
+
+fn raw<'a>(expression: &str, json: &'a str) -> Vec<&'a str> {
+    let selector = Selector::new(expression).unwrap();
+    let found: Vec<&RawValue> = selector.find_in_str(json).unwrap();
+    found.iter().map(|raw| raw.get()).collect()
+}
+
+#[test]
+fn test_find_in_str() {
+    let json = r#"{"a": [{"b": 1}, {"b": 2, "c": {"b": 3}}], "b": [4, 5]}"#;
+
+    assert_eq!(raw("$", json), vec![json]);
+    assert_eq!(raw("$.a[1].b", json), vec!["2"]);
+    assert_eq!(
+        raw("$.a.*", json),
+        vec![r#"{"b": 1}"#, r#"{"b": 2, "c": {"b": 3}}"#]
+    );
+    assert_eq!(raw("$.b[1:]", json), vec!["5"]);
+    assert_eq!(raw("$..b", json), vec!["1", "2", "3", "[4, 5]"]);
+    assert_eq!(raw("$..c..b", json), vec!["3"]);
+    assert!(raw("$.x", json).is_empty());
+}
+
+#[test]
+fn test_find_in_slice() {
+    let bytes = fs::read("tests/data.json").unwrap();
+    let selector = Selector::new("$.store.books[1:2].title").unwrap();
+    let found: Vec<Value> = selector.find_in_slice(&bytes).unwrap();
+    assert_eq!(found, vec![json!("Sword of Honour"), json!("Moby Dick")]);
+}
+
+#[test]
+fn test_filters() {
+    let json = r#"{"records": [{"id": 1, "score": 0.95}, {"id": 2, "score": 0.5},
+        {"id": 3, "score": 0.91, "nested": {"id": 4, "score": 0.99}}]}"#;
+
+    assert_eq!(raw("$.records[?(@.score > 0.9)].id", json), vec!["1", "3"]);
+    assert_eq!(
+        raw("$..[?(@.score > 0.9) && ?(@.id > 1)].id", json),
+        vec!["3", "4"]
+    );
+    assert_eq!(
+        raw("$.records[?(@.id == 2)]", json),
+        vec![r#"{"id": 2, "score": 0.5}"#]
+    );
+}
+
+#[test]
+fn test_escaped_member_names() {
+    let json = r#"{"ab": {"c": true}, "a\"b": 1}"#;
+    assert_eq!(raw("$.ab.c", json), vec!["true"]);
+    assert_eq!(raw("$.*", json), vec![r#"{"c": true}"#, "1"]);
+}
+
+#[test]
+fn test_errors() {
+    let selector = Selector::new("$.a[?(@.b == $.limit)]").unwrap();
+    let err = selector.find_in_str::<Value>("{}").unwrap_err();
+    match *err.kind() {
+        ErrorKind::Unsupported(ref feature) => {
+            assert_eq!(feature, "absolute paths in filters of raw evaluation")
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+
+    let selector = Selector::new("$.a").unwrap();
+    for json in &[r#"{"a": 1, "b": }"#, "{} {}", r#"{"a": 1"#] {
+        match *selector.find_in_str::<Value>(json).unwrap_err().kind() {
+            ErrorKind::Json(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    // Matches that cannot be deserialized as asked
+    let err = selector.find_in_str::<u8>(r#"{"a": "x"}"#).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Json(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_deep_documents() {
+    let deep = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+
+    // Rescanning the text of matches must not lift the nesting limit of serde_json
+    let json = deep(100_000);
+    for expression in &["$..*", "$[0][0]..*"] {
+        let selector = Selector::new(expression).unwrap();
+        match *selector.find_in_str::<&RawValue>(&json).unwrap_err().kind() {
+            ErrorKind::Json(_) => {}
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    let selector = Selector::new("$..*").unwrap();
+    for depth in 120..136 {
+        let json = deep(depth);
+        let parsed = serde_json::from_str::<Value>(&json);
+        let found = selector.find_in_str::<&RawValue>(&json);
+        assert_eq!(found.is_ok(), parsed.is_ok(), "depth {}", depth);
+        if let Ok(found) = found {
+            assert_eq!(found.len(), depth - 1);
+        }
+    }
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a",
+    ".b",
+    ".*",
+    "[0]",
+    "[1]",
+    "[0:1]",
+    "[1:]",
+    "..a",
+    "..*",
+    "[?(@.a > 0)]",
+    "[?(@ < 0)]",
+];
+
+proptest! {
+    #[test]
+    fn raw_finds_the_same_values(expression in expression(SEGMENTS, 6), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let mut order = vec![];
+        document_order(&value, &mut order);
+        let position = |v: &Value| order.iter().position(|&p| ::std::ptr::eq(p, v));
+
+        // Every match once, in document order
+        let mut expected: Vec<&Value> = selector.find(&value).unique().collect();
+        expected.sort_by_key(|v| position(v));
+
+        let json = serde_json::to_string_pretty(&value).unwrap();
+        let found: Vec<Value> = selector.find_in_str(&json).unwrap();
+
+        prop_assert_eq!(found.iter().collect::<Vec<&Value>>(), expected);
+    }
+}
@@ -0,0 +1,137 @@
+extern crate jsonpath;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::{ErrorKind, Selector};
+use serde_json::Value;
+use std::io::{self, BufRead, BufReader, Read};
+
+fn records(expression: &str, input: &str) -> Vec<(usize, Result<Vec<Value>, String>)> {
+    let selector = Selector::new(expression).unwrap();
+    selector
+        .find_records(input.as_bytes())
+        .map(|(record, matches)| (record, matches.map_err(|e| e.to_string())))
+        .collect()
+}
+
+#[test]
+fn test_json_lines() {
+    let input = "{\"id\": 1, \"tags\": [\"a\"]}\n{\"id\": 2, \"tags\": []}\n\n{\"id\": 3}\n";
+    assert_eq!(
+        records("$.id", input),
+        vec![
+            (1, Ok(vec![json!(1)])),
+            (2, Ok(vec![json!(2)])),
+            (3, Ok(vec![json!(3)])),
+        ]
+    );
+    assert_eq!(
+        records("$.tags.*", input),
+        vec![(1, Ok(vec![json!("a")])), (2, Ok(vec![])), (3, Ok(vec![]))]
+    );
+}
+
+#[test]
+fn test_concatenated_json() {
+    let input = "{\"id\": 1}{\"id\": 2} [3]\n{\n  \"id\": 4\n}\n5";
+    let found: Vec<usize> = records("$", input).into_iter().map(|r| r.0).collect();
+    assert_eq!(found, vec![1, 2, 3, 4, 5]);
+    assert_eq!(records("$.id", input)[3], (4, Ok(vec![json!(4)])));
+}
+
+#[test]
+fn test_malformed_records_are_skipped() {
+    let input = "{\"id\": 1}\n{\"id\": }\n{\"id\": 3}\nnot json\n{\"id\": 5}\n{\"id\"";
+    let found = records("$.id", input);
+    assert_eq!(found.len(), 6);
+    assert_eq!(found[0], (1, Ok(vec![json!(1)])));
+    assert!(found[1].1.is_err());
+    assert_eq!(found[2], (3, Ok(vec![json!(3)])));
+    assert!(found[3].1.is_err());
+    assert_eq!(found[4], (5, Ok(vec![json!(5)])));
+    assert!(found[5].1.is_err());
+}
+
+#[test]
+fn test_truncated_lines() {
+    let expected = |found: &[(usize, Result<Vec<Value>, String>)]| {
+        assert_eq!(found.len(), 4);
+        assert!(found[0].1.is_err());
+        assert_eq!(found[1], (2, Ok(vec![json!(2)])));
+        assert_eq!(found[2], (3, Ok(vec![json!(3)])));
+        assert_eq!(found[3], (4, Ok(vec![json!(4)])));
+    };
+    expected(&records(
+        "$.id",
+        "{\"id\": 1, \"tags\": [\n{\"id\": 2}\n{\"id\": 3}\n{\"id\": 4}\n",
+    ));
+    expected(&records(
+        "$.id",
+        "{\"id\": \"abc}\n{\"id\": 2}\n{\"id\": 3}\n{\"id\": 4}\n",
+    ));
+}
+
+#[test]
+fn test_multi_line_record_after_error() {
+    let input = "{\"id\": }\n{\n  \"id\": 2,\n  \"tags\": [\"}\", \"\\\"\"]\n}\n{\"id\": 3}\n";
+    let found = records("$.id", input);
+    assert_eq!(found.len(), 3);
+    assert!(found[0].1.is_err());
+    assert_eq!(found[1], (2, Ok(vec![json!(2)])));
+    assert_eq!(found[2], (3, Ok(vec![json!(3)])));
+}
+
+#[test]
+fn test_record_after_error_on_the_same_line() {
+    let input = "{\"id\": } {\"id\": 2}[1, }\"x\" {\"id\": 4}\ntru {\"id\": 6}";
+    let found = records("$.id", input);
+    assert_eq!(found.len(), 7);
+    assert!(found[0].1.is_err());
+    assert_eq!(found[1], (2, Ok(vec![json!(2)])));
+    assert!(found[2].1.is_err());
+    assert_eq!(found[3], (4, Ok(vec![])));
+    assert_eq!(found[4], (5, Ok(vec![json!(4)])));
+    assert!(found[5].1.is_err());
+    assert_eq!(found[6], (7, Ok(vec![json!(6)])));
+}
+
+#[test]
+fn test_large_records() {
+    let record = json!({ "items": (0..10_000).collect::<Vec<usize>>() });
+    let pretty = serde_json::to_string_pretty(&record).unwrap();
+    let input = format!("{}\n{}", pretty, pretty);
+
+    let selector = Selector::new("$.items[9999]").unwrap();
+    let reader = BufReader::with_capacity(16, input.as_bytes());
+    let found: Vec<_> = selector
+        .find_records(reader)
+        .map(|(record, matches)| (record, matches.unwrap()))
+        .collect();
+    assert_eq!(found, vec![(1, vec![json!(9999)]), (2, vec![json!(9999)])]);
+}
+
+// Fails after the first read
+struct BrokenReader(Option<&'static [u8]>);
+
+impl Read for BrokenReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.0.take() {
+            Some(data) => (&data[..]).read(buf),
+            None => Err(io::Error::other("broken")),
+        }
+    }
+}
+
+#[test]
+fn test_io_error_ends_the_records() {
+    let selector = Selector::new("$").unwrap();
+    let reader: Box<dyn BufRead> = Box::new(BufReader::new(BrokenReader(Some(b"1\n2"))));
+    let found: Vec<_> = selector.find_records(reader).collect();
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[0].1.as_ref().unwrap(), &vec![json!(1)]);
+    assert_eq!(found[1].1.as_ref().unwrap(), &vec![json!(2)]);
+    match *found[2].1.as_ref().unwrap_err().kind() {
+        ErrorKind::Io(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
@@ -1,7 +1,11 @@
 extern crate jsonpath;
+extern crate proptest;
 extern crate serde_json;
 
-use jsonpath::Selector;
+mod common;
+
+use common::read_data;
+use jsonpath::{ComplexityLimits, ErrorKind, Selector};
 use serde_json::Value;
 use std::fs::File;
 use std::io::Read;
@@ -33,7 +37,8 @@ macro_rules! assert_jsonpath {
             .map(|x| {
                 // println!("{:?}", x);
                 x.$convert()
-            }).filter(|x| x.is_some())
+            })
+            .filter(|x| x.is_some())
             .map(|x| x.unwrap())
             .collect();
 
@@ -190,6 +195,37 @@ fn test_filter_with_absolute_condition() {
     );
 }
 
+#[test]
+fn test_filter_with_absolute_condition_and_inclusive_operators() {
+    assert_jsonpath_str!(
+        "$.store.books[?($.store.bicycle.price >= 19.95)].title",
+        [
+            "Sayings of the Century",
+            "Sword of Honour",
+            "Moby Dick",
+            "The Lord of the Rings",
+        ]
+    );
+    let expected: [&str; 0] = [];
+    assert_jsonpath_str!(
+        "$.store.books[?($.store.bicycle.price <= 19)].title",
+        expected
+    );
+}
+
+#[test]
+fn test_filter_regex() {
+    assert_jsonpath_str!("$.store.books[?(@.title =~ /^Moby/)].title", ["Moby Dick"]);
+    assert_jsonpath_str!(
+        "$.store.books[?(@.title =~ /of the/i)].title",
+        ["Sayings of the Century", "The Lord of the Rings"]
+    );
+    assert_jsonpath_str!(
+        "$.store.books[?(@.price =~ /.*/) || ?(@.author =~ /Tolkien$/)].title",
+        ["The Lord of the Rings"]
+    );
+}
+
 #[test]
 fn test_filter_and_or() {
     assert_jsonpath_str!(
@@ -287,6 +323,44 @@ fn test_filter_with_expression_on_right_side() {
     );
 }
 
+#[test]
+fn test_filter_with_nested_absolute_expressions() {
+    assert_jsonpath_str!(
+        "$.store.books[?(@.price > $.store.books[?(@.title == $.store.books[2].title)].price)].title",
+        ["Sword of Honour", "The Lord of the Rings"]
+    );
+    assert_jsonpath_str!(
+        "$.store.books[?($.store.books[?(@.price > $.store.bicycle.price)].category == 'fiction') && ?(@.price < 9)].title",
+        ["Sayings of the Century"]
+    );
+}
+
+#[test]
+fn test_descendant() {
+    // `bicycle` is sorted before `books`, unless the order of the document is preserved
+    #[cfg(not(feature = "preserve_order"))]
+    assert_jsonpath_f64!("$..price", [19.95, 8.95, 12.99, 9.0, 22.99]);
+    #[cfg(feature = "preserve_order")]
+    assert_jsonpath_f64!("$..price", [8.95, 12.99, 9.0, 22.99, 19.95]);
+    assert_jsonpath_str!(
+        "$..title",
+        [
+            "Sayings of the Century",
+            "Sword of Honour",
+            "Sword of Honour",
+            "Moby Dick",
+            "The Lord of the Rings",
+        ]
+    );
+    assert_jsonpath_str!("$.store.books[1]..last_name", ["Anderson"]);
+    assert_jsonpath_str!("$..movies..*", ["Sword of Honour", "Bill", "Anderson"]);
+    assert_jsonpath_str!(
+        "$..books[?(@.price < 10)].title",
+        ["Sayings of the Century", "Moby Dick"]
+    );
+    assert_jsonpath_str!("$..[3].author", ["J. R. R. Tolkien"]);
+}
+
 #[test]
 fn test_root() {
     let json = r#"
@@ -296,3 +370,141 @@ fn test_root() {
     let selector = Selector::new("$").unwrap();
     let _found_values: Vec<&Value> = selector.find(&value).collect();
 }
+
+fn find_strict_errors(path: &str) -> Vec<String> {
+    let value = read_data();
+    let selector = Selector::new(path).unwrap();
+    selector
+        .find_strict(&value)
+        .filter_map(|x| x.err())
+        .map(|e| e.to_string())
+        .collect()
+}
+
+#[test]
+fn test_find_strict_matches() {
+    let value = read_data();
+    let selector = Selector::new("$.store.books[?(@.price < 10)].title").unwrap();
+    let titles: Vec<&Value> = selector
+        .find_strict(&value)
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(titles, vec!["Sayings of the Century", "Moby Dick"]);
+}
+
+#[test]
+fn test_find_strict_missing_member() {
+    assert_eq!(
+        find_strict_errors("$.store.books.*.isbn"),
+        vec![
+            "missing member ['isbn'] at $['store']['books'][0]",
+            "missing member ['isbn'] at $['store']['books'][1]",
+        ]
+    );
+    assert_eq!(
+        find_strict_errors("$.store.books[7]"),
+        vec!["missing member [7] at $['store']['books']"]
+    );
+    assert_eq!(
+        find_strict_errors("$.store.bicycle[3]"),
+        vec!["missing member [3] at $['store']['bicycle']"]
+    );
+}
+
+#[test]
+fn test_find_strict_type_mismatch() {
+    assert_eq!(
+        find_strict_errors("$.store.bicycle.color[3]"),
+        vec!["type mismatch at $['store']['bicycle']['color']: expected array, found string"]
+    );
+    assert_eq!(
+        find_strict_errors("$.store.books[?(@.price == 'cheap')].title"),
+        vec![
+            "type mismatch at $['store']['books'][0]: expected string, found number",
+            "type mismatch at $['store']['books'][1]: expected string, found number",
+            "type mismatch at $['store']['books'][2]: expected string, found number",
+            "type mismatch at $['store']['books'][3]: expected string, found number",
+        ]
+    );
+}
+
+#[test]
+fn test_find_strict_filter_errors() {
+    let errors = find_strict_errors("$.store.books[?(@.isbn == '0-553-21311-3')].title");
+    assert_eq!(
+        errors,
+        vec![
+            "missing member ['isbn'] at $['store']['books'][0]",
+            "missing member ['isbn'] at $['store']['books'][1]",
+        ]
+    );
+
+    let errors = find_strict_errors("$.store.books[?(@.price > $.store.books.*.price)].title");
+    assert_eq!(errors.len(), 4);
+    assert_eq!(
+        errors[0],
+        "comparison operand at $['store']['books'][0] yields 4 values, expected one"
+    );
+}
+
+#[test]
+fn test_find_strict_error_kind() {
+    let value = read_data();
+    let selector = Selector::new("$.store.bicycle.size").unwrap();
+    let err = selector.find_strict(&value).next().unwrap().unwrap_err();
+    match *err.kind() {
+        ErrorKind::MissingMember(ref path, ref member) => {
+            assert_eq!(path, "$['store']['bicycle']");
+            assert_eq!(member, "['size']");
+        }
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
+
+#[test]
+fn test_complexity_limits() {
+    let limits = ComplexityLimits {
+        max_descendants: Some(1),
+        max_absolute_paths: Some(1),
+        max_regex_size: Some(8),
+        max_union_fan_out: Some(2),
+        ..ComplexityLimits::default()
+    };
+    assert!(Selector::with_limits("$..books[?(@.price < $.store.bicycle.price)]", &limits).is_ok());
+
+    for &(expression, message) in &[
+        (
+            "$..books..title",
+            "descendant segments is 2, at most 1 allowed",
+        ),
+        (
+            "$.a[?(@.b == $.c) && ?(@.d == $.e)]",
+            "absolute paths in filters is 2, at most 1 allowed",
+        ),
+        (
+            "$.a[?(@.b =~ /^[a-z]+-[0-9]+$/)]",
+            "regex size is 15, at most 8 allowed",
+        ),
+        (
+            "$.a[?(@.b == 1) || ?(@.b == 2) || ?(@.c == 3)]",
+            "union fan-out is 3, at most 2 allowed",
+        ),
+        (
+            "$.a[?(@.b == ['x', 'y', 'z'])]",
+            "union fan-out is 3, at most 2 allowed",
+        ),
+    ] {
+        let err = Selector::with_limits(expression, &limits).err().unwrap();
+        match *err.kind() {
+            ErrorKind::TooComplex(ref msg) => assert_eq!(msg, message),
+            ref kind => panic!("unexpected error: {:?}", kind),
+        }
+    }
+
+    // Parse errors come first
+    let err = Selector::with_limits("$..a..b..", &limits).err().unwrap();
+    match *err.kind() {
+        ErrorKind::Parse(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+}
@@ -0,0 +1,79 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::read_data;
+use jsonpath::{Selector, SelectorSet};
+use serde_json::Value;
+
+const EXPRESSIONS: &[&str] = &[
+    "$",
+    "$.store",
+    "$.store.books.*.title",
+    "$.store.books[0:1].author",
+    "$.store.books[:2].price",
+    "$.store.books[2:].isbn",
+    "$.store.books[?(@.price < 10)].title",
+    "$.store.books[?(@.price > $.store.books[?(@.title == 'Moby Dick')].price)].title",
+    "$.store.books[?(@.category == 'fiction') && ?(@.title =~ /^The/)].author",
+    "$.store.books.*.movies.*.realisator.last_name",
+    "$.store.bicycle.price",
+    "$.store.bicycle.size",
+    "$.store.books[7]",
+    "$..price",
+    "$..books[0]..title",
+];
+
+#[test]
+fn test_find_is_the_same_as_each_selector() {
+    let data = read_data();
+    let set = SelectorSet::new(EXPRESSIONS).unwrap();
+    assert_eq!(set.len(), EXPRESSIONS.len());
+
+    let found = set.find(&data);
+    for (expression, found) in EXPRESSIONS.iter().zip(found) {
+        let selector = Selector::new(expression).unwrap();
+        let expected: Vec<&Value> = selector.find(&data).collect();
+        assert_eq!(found, expected, "{}", expression);
+    }
+}
+
+#[test]
+fn test_matches() {
+    let data = read_data();
+    let set = SelectorSet::new(EXPRESSIONS).unwrap();
+
+    let matches = set.matches(&data);
+    assert!(matches.matched_any());
+    assert_eq!(matches.len(), EXPRESSIONS.len());
+    assert_eq!(
+        matches.iter().collect::<Vec<usize>>(),
+        vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 13, 14]
+    );
+    assert!(!matches.matched(11));
+    assert!(!matches.matched(100));
+}
+
+#[test]
+fn test_duplicate_selectors() {
+    let set = SelectorSet::new(vec!["$.a".to_owned(), "$.a".to_owned()]).unwrap();
+    let data = json!({"a": 1});
+    let found = set.find(&data);
+    assert_eq!(found, vec![vec![&json!(1)], vec![&json!(1)]]);
+}
+
+#[test]
+fn test_empty_set() {
+    let set = SelectorSet::new(Vec::<String>::new()).unwrap();
+    assert!(set.is_empty());
+    assert!(set.find(&json!({})).is_empty());
+    assert!(!set.matches(&json!({})).matched_any());
+}
+
+#[test]
+fn test_invalid_expression() {
+    assert!(SelectorSet::new(["$.a", "$.b[?"]).is_err());
+}
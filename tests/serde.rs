@@ -0,0 +1,64 @@
+#![cfg(feature = "serde")]
+
+extern crate jsonpath;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::Selector;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Rule {
+    name: String,
+    path: Selector,
+    #[serde(default)]
+    fallbacks: Vec<Selector>,
+}
+
+#[test]
+fn test_deserialize() {
+    let rule: Rule = serde_json::from_str(
+        r#"{"name": "cheap", "path": "$.books[?(@.price<10)].title", "fallbacks": ["$..title"]}"#,
+    )
+    .unwrap();
+    assert_eq!(rule.path.to_string(), "$.books[?(@.price < 10)].title");
+    assert_eq!(rule.fallbacks, vec![Selector::new("$..title").unwrap()]);
+
+    let books = json!({"books": [{"title": "a", "price": 5}, {"title": "b", "price": 15}]});
+    assert_eq!(rule.path.find(&books).collect::<Vec<_>>(), vec!["a"]);
+}
+
+#[test]
+fn test_deserialize_invalid_expression() {
+    let err =
+        serde_json::from_str::<Rule>(r#"{"name": "broken", "path": "$.books[?(@.price <> 3)]"}"#)
+            .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid JSONPath expression `$.books[?(@.price <> 3)]`: unexpected `>` at line 1, \
+         column 20, expected a number, a string literal, an array or a path at line 1 column 53"
+    );
+
+    let err = serde_json::from_str::<Rule>(r#"{"name": "number", "path": 3}"#).unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("invalid type: integer `3`, expected a JSONPath expression"));
+}
+
+#[test]
+fn test_serialize() {
+    let rule = Rule {
+        name: "cheap".to_owned(),
+        path: Selector::new("$.books[?(@.price<10.0)]").unwrap(),
+        fallbacks: vec![],
+    };
+    assert_eq!(
+        serde_json::to_value(&rule).unwrap(),
+        json!({"name": "cheap", "path": "$.books[?(@.price < 10)]", "fallbacks": []})
+    );
+
+    let json = serde_json::to_string(&rule).unwrap();
+    let parsed: Rule = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.path, rule.path);
+}
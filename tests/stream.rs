@@ -0,0 +1,150 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{document_order, expression, json};
+use jsonpath::{ErrorKind, IndexedDocument, Selector, StreamMatch};
+use proptest::prelude::*;
+use serde_json::Value;
+use std::fs::File;
+use std::io::BufReader;
+
+fn stream(expression: &str, json: &str) -> Vec<StreamMatch> {
+    let selector = Selector::new(expression).unwrap();
+    let mut found = vec![];
+    selector
+        .stream_slice(json.as_bytes(), |m| found.push(m))
+        .unwrap();
+    found
+}
+
+fn paths(found: &[StreamMatch]) -> Vec<&str> {
+    found.iter().map(|m| m.path.as_str()).collect()
+}
+
+#[test]
+fn test_stream_reader() {
+    let selector = Selector::new("$.store.books[1:2].title").unwrap();
+    let reader = BufReader::new(File::open("tests/data.json").unwrap());
+    let mut found = vec![];
+    selector.stream(reader, |m| found.push(m)).unwrap();
+
+    assert_eq!(
+        found,
+        vec![
+            StreamMatch {
+                path: "$['store']['books'][1]['title']".to_owned(),
+                value: json!("Sword of Honour"),
+            },
+            StreamMatch {
+                path: "$['store']['books'][2]['title']".to_owned(),
+                value: json!("Moby Dick"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_stream_segments() {
+    let json = r#"{"a": [{"b": 1}, {"b": 2, "c": {"b": 3}}], "b": [4, 5]}"#;
+
+    assert_eq!(paths(&stream("$", json)), vec!["$"]);
+    assert_eq!(paths(&stream("$.a[1].b", json)), vec!["$['a'][1]['b']"]);
+    assert_eq!(
+        paths(&stream("$.a.*", json)),
+        vec!["$['a'][0]", "$['a'][1]"]
+    );
+    assert_eq!(paths(&stream("$.b[:1]", json)), vec!["$['b'][0]"]);
+    assert_eq!(paths(&stream("$.b[1:]", json)), vec!["$['b'][1]"]);
+    assert_eq!(
+        paths(&stream("$..b", json)),
+        vec![
+            "$['a'][0]['b']",
+            "$['a'][1]['b']",
+            "$['a'][1]['c']['b']",
+            "$['b']",
+        ]
+    );
+    assert_eq!(
+        paths(&stream("$..[1]", json)),
+        vec!["$['a'][1]", "$['b'][1]"]
+    );
+}
+
+#[test]
+fn test_stream_matches_inside_matches() {
+    let json = r#"{"a": {"x": 1, "a": {"x": 2}}}"#;
+    let found = stream("$..a", json);
+    assert_eq!(paths(&found), vec!["$['a']", "$['a']['a']"]);
+    assert_eq!(found[0].value, json!({"x": 1, "a": {"x": 2}}));
+    assert_eq!(found[1].value, json!({"x": 2}));
+}
+
+#[test]
+fn test_stream_errors() {
+    let selector = Selector::new("$.a[?(@.b == 1)]").unwrap();
+    let err = selector.stream_slice(b"{}", |_| ()).unwrap_err();
+    match *err.kind() {
+        ErrorKind::Unsupported(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+
+    // Matches before the error are emitted
+    let selector = Selector::new("$.*").unwrap();
+    let mut found = vec![];
+    let err = selector
+        .stream_slice(br#"{"a": 1, "b": 2, "c": }"#, |m| found.push(m))
+        .unwrap_err();
+    match *err.kind() {
+        ErrorKind::Json(_) => {}
+        ref kind => panic!("unexpected error: {:?}", kind),
+    }
+    assert_eq!(paths(&found), vec!["$['a']", "$['b']"]);
+
+    assert!(selector.stream_slice(b"{} {}", |_| ()).is_err());
+}
+
+#[test]
+fn test_descendants_in_document_order() {
+    let json = r#"{"a": {"b": 1}, "c": 2}"#;
+    let found = stream("$..*", json);
+    assert_eq!(paths(&found), vec!["$['a']", "$['a']['b']", "$['c']"]);
+
+    let value: Value = serde_json::from_str(json).unwrap();
+    let selector = Selector::new("$..*").unwrap();
+    let values: Vec<&Value> = selector.find(&value).collect();
+    assert_eq!(values, vec![&json!({"b": 1}), &json!(2), &json!(1)]);
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a", ".b", ".*", "[0]", "[1]", "[0:1]", "[:1]", "[1:]", "..a", "..*", "..[0]",
+];
+
+proptest! {
+    #[test]
+    fn stream_finds_the_same_values(expression in expression(SEGMENTS, 6), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let mut order = vec![];
+        document_order(&value, &mut order);
+        let position = |v: &Value| order.iter().position(|&p| ::std::ptr::eq(p, v));
+
+        // Every match once, in document order
+        let mut expected: Vec<&Value> = selector.find(&value).unique().collect();
+        expected.sort_by_key(|v| position(v));
+        let index = IndexedDocument::new(&value);
+        let expected: Vec<(String, &Value)> = expected
+            .into_iter()
+            .map(|v| (index.path(v).unwrap(), v))
+            .collect();
+
+        let mut found = vec![];
+        let bytes = serde_json::to_vec(&value).unwrap();
+        selector.stream_slice(&bytes, |m| found.push(m)).unwrap();
+        let found: Vec<(String, &Value)> = found.iter().map(|m| (m.path.clone(), &m.value)).collect();
+
+        prop_assert_eq!(found, expected);
+    }
+}
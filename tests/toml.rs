@@ -0,0 +1,78 @@
+#![cfg(feature = "toml")]
+
+extern crate jsonpath;
+extern crate toml;
+
+use jsonpath::{Document, Kind, Selector};
+use toml::Value;
+
+const MANIFEST: &str = r#"
+[package]
+name = "jsonpath"
+version = "0.1.2"
+published = 2018-02-11T10:30:00Z
+
+[dependencies]
+serde = "1.0"
+regex = { version = "1", optional = true }
+
+[[bench]]
+name = "wide"
+harness = false
+
+[[bench]]
+name = "deep"
+harness = true
+"#;
+
+fn find<'a>(expression: &str, root: &'a Value) -> Vec<&'a Value> {
+    Selector::new(expression).unwrap().find(root).collect()
+}
+
+fn names(expression: &str, root: &Value) -> Vec<String> {
+    find(expression, root)
+        .iter()
+        .map(|value| value.as_str().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn test_find() {
+    let root: Value = MANIFEST.parse().unwrap();
+    assert_eq!(names("$.package.name", &root), vec!["jsonpath"]);
+    assert_eq!(names("$.bench.*.name", &root), vec!["wide", "deep"]);
+    assert_eq!(names("$.dependencies.regex.version", &root), vec!["1"]);
+    assert_eq!(
+        find("$.bench[?(@.name == 'deep')].harness", &root),
+        vec![&Value::Boolean(true)]
+    );
+}
+
+#[test]
+fn test_datetimes_are_strings() {
+    let root: Value = MANIFEST.parse().unwrap();
+    let published = find("$.package.published", &root);
+    assert_eq!(published[0].kind(), Kind::String);
+    assert_eq!(
+        Document::as_str(published[0]).unwrap(),
+        "2018-02-11T10:30:00Z"
+    );
+
+    assert_eq!(
+        names("$[?(@.published > '2018-01-01')].name", &root),
+        vec!["jsonpath"]
+    );
+    assert_eq!(
+        names("$[?(@.published =~ /^2018-02/)].name", &root),
+        vec!["jsonpath"]
+    );
+}
+
+#[test]
+fn test_numbers() {
+    let root: Value = "limits = [1, 2.5, 10]\n".parse().unwrap();
+    assert_eq!(
+        find("$.limits[?(@ > 2)]", &root),
+        vec![&Value::Float(2.5), &Value::Integer(10)]
+    );
+}
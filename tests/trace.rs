@@ -0,0 +1,152 @@
+extern crate jsonpath;
+extern crate proptest;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{expression, json};
+use jsonpath::{ComparisonTrace, Outcome, Selector, TraceStep};
+use proptest::prelude::*;
+use serde_json::Value;
+
+fn step(path: &str, segment: &str, selected: &[&str]) -> TraceStep {
+    TraceStep {
+        path: path.to_owned(),
+        segment: segment.to_owned(),
+        outcome: Outcome::Selected(selected.iter().map(|p| p.to_string()).collect()),
+    }
+}
+
+#[test]
+fn test_trace_paths() {
+    let value = json!({"a": [{"b": 1}, {"c": 2}]});
+    let trace = Selector::new("$.a.*.b").unwrap().trace(&value);
+
+    assert_eq!(
+        trace.steps,
+        vec![
+            step("$", "root", &["$"]),
+            step("$", "member \"a\"", &["$['a']"]),
+            step("$['a']", "wildcard", &["$['a'][0]", "$['a'][1]"]),
+            step("$['a'][0]", "member \"b\"", &["$['a'][0]['b']"]),
+            step("$['a'][1]", "member \"b\"", &[]),
+        ]
+    );
+    assert_eq!(trace.matches, vec![1]);
+}
+
+#[test]
+fn test_trace_descendants() {
+    let value = json!({"a": {"b": 1}});
+    let trace = Selector::new("$..b").unwrap().trace(&value);
+    assert_eq!(
+        trace.to_string(),
+        "$: root -> $\n\
+         $: descendants -> $['a']\n\
+         $: member \"b\" -> nothing\n\
+         $['a']: descendants -> $['a']['b']\n\
+         $['a']: member \"b\" -> $['a']['b']\n\
+         $['a']['b']: descendants -> nothing\n\
+         $['a']['b']: member \"b\" -> nothing\n"
+    );
+}
+
+#[test]
+fn test_trace_filters() {
+    let value = json!({
+        "limit": 10,
+        "items": [{"price": 5, "tag": "x"}, {"price": 20, "tag": "y"}]
+    });
+    let selector = Selector::new("$.items[?(@.price < $.limit) || ?(@.tag == 'y')].price").unwrap();
+    let trace = selector.trace(&value);
+    assert_eq!(trace.matches, vec![5, 20]);
+
+    let filtered: Vec<&Outcome> = trace
+        .steps
+        .iter()
+        .filter(|step| step.segment == "filter")
+        .map(|step| &step.outcome)
+        .collect();
+
+    // `||` stops at the first comparison that holds
+    assert_eq!(
+        filtered,
+        vec![
+            &Outcome::Filtered {
+                matched: true,
+                comparisons: vec![ComparisonTrace {
+                    left: vec![json!(5)],
+                    operator: "<".to_owned(),
+                    right: vec![json!(10)],
+                    result: true,
+                }],
+            },
+            &Outcome::Filtered {
+                matched: true,
+                comparisons: vec![
+                    ComparisonTrace {
+                        left: vec![json!(20)],
+                        operator: "<".to_owned(),
+                        right: vec![json!(10)],
+                        result: false,
+                    },
+                    ComparisonTrace {
+                        left: vec![json!("y")],
+                        operator: "==".to_owned(),
+                        right: vec![json!("y")],
+                        result: true,
+                    },
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_trace_regex_and_arrays() {
+    let value = json!([{"t": "Moby Dick"}, {"t": "Ulysses"}]);
+
+    let trace = Selector::new("$[?(@.t =~ /^moby/i)]")
+        .unwrap()
+        .trace(&value);
+    assert_eq!(trace.matches, vec![&value[0]]);
+    assert!(trace
+        .to_string()
+        .contains("[\"Ulysses\"] =~ [\"(?i)^moby\"] -> false"));
+
+    let trace = Selector::new("$[?(@.t == ['Ulysses', 'Emma'])]")
+        .unwrap()
+        .trace(&value);
+    assert_eq!(trace.matches, vec![&value[1]]);
+    assert!(trace
+        .to_string()
+        .contains("[\"Ulysses\"] == [\"Ulysses\",\"Emma\"] -> true"));
+}
+
+const SEGMENTS: &[&str] = &[
+    ".a",
+    ".b",
+    ".*",
+    "[0]",
+    "[0:1]",
+    "..a",
+    "..*",
+    "[?(@.a > 0)]",
+    "[?(@ < $.b)]",
+];
+
+proptest! {
+    #[test]
+    fn trace_finds_what_find_does(expression in expression(SEGMENTS, 5), value in json()) {
+        let selector = Selector::new(&expression).unwrap();
+        let expected: Vec<*const Value> = selector.find(&value).map(|v| v as *const Value).collect();
+        let traced: Vec<*const Value> = selector
+            .trace(&value)
+            .matches
+            .into_iter()
+            .map(|v| v as *const Value)
+            .collect();
+        prop_assert_eq!(traced, expected);
+    }
+}
@@ -0,0 +1,122 @@
+extern crate jsonpath;
+#[macro_use]
+extern crate serde_json;
+
+use jsonpath::ast::{Operand, Path, Segment, Start};
+use jsonpath::visit::{walk_operand_mut, walk_path_mut, walk_segment, Visitor, VisitorMut};
+use jsonpath::{Result, Selector};
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+struct Tenant(&'static str);
+
+impl VisitorMut for Tenant {
+    fn visit_path_mut(&mut self, path: &mut Path) -> Result<()> {
+        if path.start == Start::Root {
+            let base = vec![
+                Segment::Child("tenants".to_owned()),
+                Segment::Child(self.0.to_owned()),
+            ];
+            path.segments.splice(0..0, base);
+        }
+        walk_path_mut(self, path)
+    }
+}
+
+#[derive(Default)]
+struct Members(BTreeSet<String>);
+
+impl Visitor for Members {
+    fn visit_segment(&mut self, segment: &Segment) -> Result<()> {
+        if let Segment::Child(ref name) = *segment {
+            self.0.insert(name.clone());
+        }
+        walk_segment(self, segment)
+    }
+}
+
+struct Replace(&'static str, &'static str);
+
+impl VisitorMut for Replace {
+    fn visit_operand_mut(&mut self, operand: &mut Operand) -> Result<()> {
+        if let Operand::String(ref mut content) = *operand {
+            if content == self.0 {
+                *content = self.1.to_owned();
+            }
+        }
+        walk_operand_mut(self, operand)
+    }
+}
+
+struct NoDescendants;
+
+impl Visitor for NoDescendants {
+    fn visit_segment(&mut self, segment: &Segment) -> Result<()> {
+        match *segment {
+            Segment::Descendant => Err("`..` is not allowed".into()),
+            _ => walk_segment(self, segment),
+        }
+    }
+}
+
+fn rewrite<V: VisitorMut>(expression: &str, visitor: &mut V) -> Selector {
+    let mut path = Selector::new(expression).unwrap().to_path();
+    visitor.visit_path_mut(&mut path).unwrap();
+    path.to_selector().unwrap()
+}
+
+#[test]
+fn test_prefix_tenant() {
+    let selector = rewrite(
+        "$.orders[?(@.total > $.limit) || ?(@.id == $.pinned[0])].id",
+        &mut Tenant("acme"),
+    );
+    assert_eq!(
+        selector.to_string(),
+        "$.tenants.acme.orders[?(@.total > $.tenants.acme.limit) \
+         || ?(@.id == $.tenants.acme.pinned[0])].id"
+    );
+
+    let value = json!({
+        "orders": [{"id": 0, "total": 100}],
+        "tenants": {"acme": {"limit": 10, "orders": [{"id": 1, "total": 20}]}}
+    });
+    let ids: Vec<&Value> = selector.find(&value).collect();
+    assert_eq!(ids, vec![&json!(1)]);
+}
+
+#[test]
+fn test_list_members() {
+    let selector = Selector::new("$.store..book[?(@.author.name == $.who)].title").unwrap();
+    let mut members = Members::default();
+    members.visit_path(&selector.to_path()).unwrap();
+    let names: Vec<&str> = members.0.iter().map(String::as_str).collect();
+    assert_eq!(
+        names,
+        vec!["author", "book", "name", "store", "title", "who"]
+    );
+}
+
+#[test]
+fn test_replace_literals() {
+    let selector = rewrite(
+        "$.users[?(@.role == 'admin') || ?(@.roles == ['guest', 'admin'])].name",
+        &mut Replace("admin", "owner"),
+    );
+    assert_eq!(
+        selector.to_string(),
+        "$.users[?(@.role == 'owner') || ?(@.roles == ['guest', 'owner'])].name"
+    );
+}
+
+#[test]
+fn test_reject_segments() {
+    let allowed = Selector::new("$.a[?(@.b == $.c[0])]").unwrap();
+    assert!(NoDescendants.visit_path(&allowed.to_path()).is_ok());
+
+    for expression in &["$..a", "$.a[?(@.b == $..c)]"] {
+        let rejected = Selector::new(expression).unwrap();
+        let err = NoDescendants.visit_path(&rejected.to_path()).unwrap_err();
+        assert_eq!(err.to_string(), "`..` is not allowed");
+    }
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "serde_yaml")]
+
+extern crate jsonpath;
+extern crate serde_yaml;
+
+use jsonpath::{Document, ErrorKind, Kind, Selector};
+use serde_yaml::Value;
+
+const DEPLOYMENT: &str = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+  labels:
+    app: web
+spec:
+  replicas: 3
+  template:
+    spec:
+      containers:
+        - name: nginx
+          image: nginx:1.25
+          ports:
+            - containerPort: 80
+        - name: sidecar
+          image: envoy:1.28
+          ports:
+            - containerPort: 9901
+"#;
+
+fn find<'a>(expression: &str, root: &'a Value) -> Vec<&'a Value> {
+    Selector::new(expression).unwrap().find(root).collect()
+}
+
+fn yaml(source: &str) -> Value {
+    serde_yaml::from_str(source).unwrap()
+}
+
+#[test]
+fn test_find() {
+    let root = yaml(DEPLOYMENT);
+    assert_eq!(find("$.metadata.name", &root), vec!["web"]);
+    assert_eq!(find("$.spec.replicas", &root), vec![3]);
+    assert_eq!(
+        find("$.spec.template.spec.containers.*.image", &root),
+        vec!["nginx:1.25", "envoy:1.28"]
+    );
+    assert_eq!(
+        find(
+            "$..containers[?(@.ports[0].containerPort > 1024)].name",
+            &root
+        ),
+        vec!["sidecar"]
+    );
+    assert_eq!(find("$..containerPort", &root), vec![80, 9901]);
+}
+
+#[test]
+fn test_tagged_values() {
+    let root = yaml("secret: !vault\n  key: db\n  version: 2\nports: !ports [80, 443]\n");
+    assert_eq!(root["secret"].kind(), Kind::Object);
+    assert_eq!(find("$.secret.key", &root), vec!["db"]);
+    assert_eq!(find("$.ports[1]", &root), vec![443]);
+    assert_eq!(find("$[?(@.version == 2)].key", &root), vec!["db"]);
+}
+
+#[test]
+fn test_non_string_keys() {
+    let root = yaml("codes:\n  200: ok\n  404: missing\n  'name': codes\n  true: yes\n");
+    assert_eq!(find("$.codes.name", &root), vec!["codes"]);
+    assert_eq!(find("$.codes[404]", &root), vec!["missing"]);
+    let offsets = yaml("-1: last\n1: first\n");
+    assert_eq!(find("$[-1]", &offsets), vec!["last"]);
+    assert_eq!(
+        find("$.codes.*", &root),
+        vec!["ok", "missing", "codes", "yes"]
+    );
+
+    // Normalized paths spell the keys as in YAML
+    let selector = Selector::new("$.codes.*.length").unwrap();
+    let paths: Vec<String> = selector
+        .find_strict(&root)
+        .map(|found| match *found.unwrap_err().kind() {
+            ErrorKind::TypeMismatch(ref path, _, _) => path.clone(),
+            ref kind => panic!("unexpected error: {:?}", kind),
+        })
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "$['codes'][200]",
+            "$['codes'][404]",
+            "$['codes']['name']",
+            "$['codes'][true]"
+        ]
+    );
+}